@@ -0,0 +1,28 @@
+//! Serves a fake registry API with the fixtures of the test suite, to try the tool without a
+//! Scaleway account. This is only meant for development and demos.
+//!
+//! Run it with `cargo run --example mock_server` and follow the printed instructions.
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+use std::thread;
+
+fn main() {
+    let server = support::registry();
+
+    println!("Serving a fake registry API at http://{}", server.addr());
+    println!();
+    println!("Try it in another terminal with:");
+    println!();
+    println!(
+        "  cargo run -- {} mynamespace/myimage --keep-last 5",
+        support::mock_args(server.addr()).join(" ")
+    );
+    println!();
+    println!("Press Ctrl-C to stop");
+
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use scaleway_sdk::registry::{Image, Namespace};
+
+/// The namespaces and images of a region as they were listed at `fetched_at`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Listing {
+    pub fetched_at: DateTime<Utc>,
+    pub namespaces: Vec<Namespace>,
+    pub images: Vec<Image>,
+}
+
+/// A cache of the namespace and image listings of each region and account, stored as a JSON file
+/// between runs
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Cache {
+    #[serde(default)]
+    listings: HashMap<String, Listing>,
+}
+
+impl Cache {
+    /// Returns the key of the listing of `region` fetched from the API at `endpoint`, or the
+    /// default API if not given, with the `token`
+    ///
+    /// Only a fingerprint of the token is part of the key, so that the token itself isn't written
+    /// to the cache file.
+    pub fn key(region: &str, endpoint: Option<&str>, token: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+
+        token.hash(&mut hasher);
+
+        format!(
+            "{} {} {:016x}",
+            region,
+            endpoint.unwrap_or("default"),
+            hasher.finish()
+        )
+    }
+
+    /// Loads the cache file at `path`
+    ///
+    /// A cache that is missing or can't be parsed is treated as empty, so that the listings are
+    /// fetched from the API instead.
+    pub fn load(path: &Path) -> Cache {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Cache::default(),
+            Err(err) => {
+                warn!("Unable to read cache file {}: {}", path.display(), err);
+
+                return Cache::default();
+            }
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Ignoring invalid cache file {}: {}", path.display(), err);
+
+            Cache::default()
+        })
+    }
+
+    /// Writes the cache to the file at `path`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string(self)?;
+
+        fs::write(path, contents)
+    }
+
+    /// Returns the listing with the given `key` unless it's missing or older than `max_age` at
+    /// `now`
+    pub fn get(&self, key: &str, max_age: Duration, now: DateTime<Utc>) -> Option<&Listing> {
+        self.listings
+            .get(key)
+            .filter(|listing| now - listing.fetched_at <= max_age)
+    }
+
+    /// Stores the `listing` with the given `key`, replacing any previous listing
+    pub fn insert(&mut self, key: &str, listing: Listing) {
+        self.listings.insert(key.to_owned(), listing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+
+    fn listing(fetched_at: DateTime<Utc>) -> Listing {
+        Listing {
+            fetched_at,
+            namespaces: vec![test_helpers::namespace("ns")],
+            images: vec![test_helpers::image("app")],
+        }
+    }
+
+    #[test]
+    fn it_returns_fresh_listings() {
+        let now = Utc::now();
+        let mut cache = Cache::default();
+
+        cache.insert("nl-ams", listing(now - Duration::minutes(4)));
+
+        let cached = cache.get("nl-ams", Duration::minutes(5), now).unwrap();
+
+        assert_eq!(cached.images[0].name(), "app");
+    }
+
+    #[test]
+    fn it_expires_listings_after_max_age() {
+        let now = Utc::now();
+        let mut cache = Cache::default();
+
+        cache.insert("nl-ams", listing(now - Duration::minutes(6)));
+
+        assert!(cache.get("nl-ams", Duration::minutes(5), now).is_none());
+    }
+
+    #[test]
+    fn it_keys_listings_by_region() {
+        let now = Utc::now();
+        let mut cache = Cache::default();
+
+        cache.insert("nl-ams", listing(now));
+
+        assert!(cache.get("fr-par", Duration::minutes(5), now).is_none());
+    }
+
+    #[test]
+    fn it_keys_listings_by_region_endpoint_and_token() {
+        let key = Cache::key("nl-ams", None, "token");
+
+        assert_eq!(key, Cache::key("nl-ams", None, "token"));
+        assert_ne!(key, Cache::key("fr-par", None, "token"));
+        assert_ne!(key, Cache::key("nl-ams", Some("http://localhost"), "token"));
+        assert_ne!(key, Cache::key("nl-ams", None, "other-token"));
+        assert!(!key.contains("token"));
+    }
+
+    #[test]
+    fn it_round_trips_through_a_file() {
+        let now = Utc::now();
+        let path =
+            std::env::temp_dir().join(format!("scw-prune-cache-{}.json", std::process::id()));
+        let mut cache = Cache::default();
+
+        cache.insert("nl-ams", listing(now));
+        cache.save(&path).unwrap();
+
+        let loaded = Cache::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let cached = loaded.get("nl-ams", Duration::minutes(5), now).unwrap();
+
+        assert_eq!(cached.namespaces[0].name(), "ns");
+        assert_eq!(cached.images[0].name(), "app");
+    }
+
+    #[test]
+    fn it_treats_invalid_cache_files_as_empty() {
+        let path =
+            std::env::temp_dir().join(format!("scw-prune-invalid-{}.json", std::process::id()));
+
+        fs::write(&path, "not json").unwrap();
+
+        let cache = Cache::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(cache.listings.is_empty());
+    }
+}
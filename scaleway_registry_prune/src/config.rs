@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::Error;
+
+/// The settings of a single profile in the Scaleway config file
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Profile {
+    pub secret_key: Option<String>,
+    pub default_region: Option<String>,
+}
+
+impl Profile {
+    /// Returns a profile where the unset fields of `self` are taken from `fallback`
+    fn or(self, fallback: &Profile) -> Profile {
+        Profile {
+            secret_key: self.secret_key.or_else(|| fallback.secret_key.clone()),
+            default_region: self
+                .default_region
+                .or_else(|| fallback.default_region.clone()),
+        }
+    }
+}
+
+/// The config file used by the official Scaleway CLI
+///
+/// The top-level settings make up the default profile, while named profiles inherit any setting
+/// they don't set themselves from the default profile.
+#[derive(Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(flatten)]
+    default: Profile,
+    active_profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+impl Config {
+    /// Returns the path of the config file, which is `$SCW_CONFIG_PATH` if set and otherwise
+    /// `scw/config.yaml` in the user's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("SCW_CONFIG_PATH") {
+            return Some(PathBuf::from(path));
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+        Some(config_dir.join("scw").join("config.yaml"))
+    }
+
+    /// Loads the config file at `path`, or returns an empty config if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(Error::ConfigReadError(err)),
+        }
+    }
+
+    /// Parses the YAML `contents` of a config file
+    pub fn parse(contents: &str) -> Result<Config, Error> {
+        serde_yaml::from_str(contents).map_err(Error::ConfigParseError)
+    }
+
+    /// Returns the profile with the given `name`, or the active profile if no name is given
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, Error> {
+        match name.or(self.active_profile.as_deref()) {
+            Some("default") | None => Ok(self.default.clone()),
+            Some(name) => self
+                .profiles
+                .get(name)
+                .map(|profile| profile.clone().or(&self.default))
+                .ok_or_else(|| Error::NoSuchProfile(name.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+access_key: SCWXXXXXXXXXXXXXXXXX
+secret_key: 11111111-1111-1111-1111-111111111111
+default_region: fr-par
+default_zone: fr-par-1
+profiles:
+  amsterdam:
+    default_region: nl-ams
+  other:
+    secret_key: 22222222-2222-2222-2222-222222222222
+"#;
+
+    #[test]
+    fn it_returns_the_default_profile() {
+        let config = Config::parse(CONFIG).unwrap();
+        let profile = config.profile(None).unwrap();
+
+        assert_eq!(
+            profile.secret_key.as_deref(),
+            Some("11111111-1111-1111-1111-111111111111")
+        );
+        assert_eq!(profile.default_region.as_deref(), Some("fr-par"));
+    }
+
+    #[test]
+    fn it_inherits_unset_fields_from_the_default_profile() {
+        let config = Config::parse(CONFIG).unwrap();
+        let profile = config.profile(Some("amsterdam")).unwrap();
+
+        assert_eq!(
+            profile.secret_key.as_deref(),
+            Some("11111111-1111-1111-1111-111111111111")
+        );
+        assert_eq!(profile.default_region.as_deref(), Some("nl-ams"));
+    }
+
+    #[test]
+    fn it_uses_the_active_profile() {
+        let config = Config::parse(&format!("{}active_profile: other\n", CONFIG)).unwrap();
+        let profile = config.profile(None).unwrap();
+
+        assert_eq!(
+            profile.secret_key.as_deref(),
+            Some("22222222-2222-2222-2222-222222222222")
+        );
+    }
+
+    #[test]
+    fn it_returns_error_for_unknown_profile() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        assert!(matches!(
+            config.profile(Some("missing")),
+            Err(Error::NoSuchProfile(_))
+        ));
+    }
+
+    #[test]
+    fn it_returns_empty_config_when_file_is_missing() {
+        let config = Config::load(Path::new("/nonexistent/scw/config.yaml")).unwrap();
+
+        assert_eq!(config.profile(None).unwrap(), Profile::default());
+    }
+}
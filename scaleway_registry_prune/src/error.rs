@@ -1,23 +1,133 @@
-use failure::Fail;
-use scaleway_sdk::Error as ScalewaySdkError;
+use scaleway_sdk::{Error as ScalewaySdkError, StatusCode};
+use thiserror::Error;
 
-#[derive(Fail, Debug)]
+use crate::interrupt::INTERRUPTED_EXIT_CODE;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
 pub enum Error {
     /// Error that indicates there was a problem talking to the API through the SDK
-    #[fail(display = "SDK error: {}", _0)]
-    ApiError(#[fail(cause)] ScalewaySdkError),
-    #[fail(display = "No image tags matches the given criteria")]
+    #[error("SDK error: {0}")]
+    ApiError(#[source] ScalewaySdkError),
+    /// Error that indicates the API rejected the authentication token
+    #[error("Authentication failed, please check your token")]
+    AuthenticationFailed,
+    /// Error that indicates the token is valid but isn't allowed to access the registry
+    #[error("Access denied, please check the permissions of your token")]
+    AccessDenied,
+    #[error("No image tags matches the given criteria")]
     NoMatchingImageTagsError,
-    #[fail(display = "No such namespace")]
+    #[error("No such namespace")]
     NoSuchNamespace,
-    #[fail(display = "No such image")]
+    #[error("No such image")]
     NoSuchImage,
-    #[fail(display = "The image has no tags associated with it")]
+    #[error("No such tag: {0}")]
+    NoSuchTag(String),
+    #[error("No tag of {0} has the digest {1}")]
+    NoSuchDigest(String, String),
+    #[error("The image has no tags associated with it")]
     NoImageTagsError,
+    #[error(
+        "Refusing to delete without confirmation as stdin is not a terminal, pass --yes to skip the prompt"
+    )]
+    NonInteractiveConfirmation,
+    #[error("Refusing to select tags interactively as stdin or stderr is not a terminal")]
+    NonInteractiveSelection,
+    #[error("Unable to select tags interactively: {0}")]
+    SelectionError(#[source] std::io::Error),
+    #[error("Failed to delete {0} image tags")]
+    FailedDeletions(usize),
+    #[error("Interrupted before {0} of the tags were deleted")]
+    Interrupted(usize),
+    #[error("Unable to prune {0} of the given images")]
+    FailedTargets(usize),
+    #[error("Unable to read the list of images: {0}")]
+    TargetsReadError(#[source] std::io::Error),
+    #[error("Malformed image on line {line} of the list of images: `{content}'")]
+    MalformedTarget { line: usize, content: String },
+    #[error("Unable to read the plan file: {0}")]
+    PlanFileReadError(#[source] std::io::Error),
+    #[error("Unable to write the plan file: {0}")]
+    PlanFileWriteError(#[source] std::io::Error),
+    #[error("The plan was made for the {0} region")]
+    PlanRegionMismatch(String),
+    /// Error that indicates a planned image or tag is gone or was pushed again since planning
+    #[error("The plan is out of date, {0} no longer exists or has changed")]
+    StalePlan(String),
+    #[error("Unable to run the pre-delete hook: {0}")]
+    PreDeleteHookError(#[source] std::io::Error),
+    #[error("The pre-delete hook failed with {0}, nothing was deleted")]
+    PreDeleteHookFailed(std::process::ExitStatus),
+    #[error("Unable to write the metrics file: {0}")]
+    MetricsWriteError(#[source] std::io::Error),
+    #[error("Unable to open the resume log: {0}")]
+    ResumeLogError(#[source] std::io::Error),
+    #[error("Unable to read the Scaleway config file: {0}")]
+    ConfigReadError(#[source] std::io::Error),
+    #[error("Unable to parse the Scaleway config file: {0}")]
+    ConfigParseError(#[source] serde_yaml::Error),
+    #[error("No such profile: {0}")]
+    NoSuchProfile(String),
+    #[error("No region given, pass --region or set SCW_REGION")]
+    MissingRegion,
+    #[error("Invalid default_region in the Scaleway config file: {0}")]
+    InvalidConfigRegion(String),
+    #[error("No credentials given, pass --scw-token or --secret-key")]
+    MissingCredentials,
+    /// Error that indicates the retention rules are dangerous or contradictory
+    #[error("Invalid retention rules: {0}")]
+    InvalidFilter(&'static str),
+    #[error("Invalid proxy URL: {0}")]
+    InvalidProxy(#[source] ScalewaySdkError),
+}
+
+impl Error {
+    /// Returns the exit code of the process when it fails with this error
+    ///
+    /// | Code | Error                                        |
+    /// |------|----------------------------------------------|
+    /// | 1    | Any other error                              |
+    /// | 2    | The namespace doesn't exist                  |
+    /// | 3    | The image or tag doesn't exist               |
+    /// | 4    | Authentication failed or access was denied   |
+    /// | 5    | The API returned an error                    |
+    /// | 6    | Some of the tags couldn't be deleted         |
+    /// | 7    | The region, credentials or config is invalid |
+    /// | 130  | Interrupted with Ctrl-C                      |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoSuchNamespace => 2,
+            Error::NoSuchImage
+            | Error::NoSuchTag(_)
+            | Error::NoSuchDigest(..)
+            | Error::StalePlan(_) => 3,
+            Error::AuthenticationFailed | Error::AccessDenied => 4,
+            Error::ApiError(_) => 5,
+            Error::FailedDeletions(_) => 6,
+            Error::ConfigReadError(_)
+            | Error::ConfigParseError(_)
+            | Error::NoSuchProfile(_)
+            | Error::MissingRegion
+            | Error::InvalidConfigRegion(_)
+            | Error::MissingCredentials
+            | Error::InvalidProxy(_) => 7,
+            Error::Interrupted(_) => INTERRUPTED_EXIT_CODE,
+            _ => 1,
+        }
+    }
 }
 
 impl From<ScalewaySdkError> for Error {
     fn from(err: ScalewaySdkError) -> Error {
-        Error::ApiError(err)
+        match err {
+            ScalewaySdkError::Http { status, .. } if status == StatusCode::UNAUTHORIZED => {
+                Error::AuthenticationFailed
+            }
+            ScalewaySdkError::Http { status, .. } if status == StatusCode::FORBIDDEN => {
+                Error::AccessDenied
+            }
+            ScalewaySdkError::NoSuchNamespace => Error::NoSuchNamespace,
+            err => Error::ApiError(err),
+        }
     }
 }
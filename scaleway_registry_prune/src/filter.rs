@@ -0,0 +1,1099 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Utc};
+use globset::GlobSet;
+use regex::Regex;
+use semver::Version;
+
+use scaleway_sdk::registry::ImageTag;
+
+use crate::error::Error;
+
+/// The timestamp of a tag that the time-based retention rules compare against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AgeField {
+    /// The time the tag was last updated, which is bumped every time the tag is re-pushed
+    Updated,
+    /// The time the tag was first created
+    Created,
+}
+
+impl FromStr for AgeField {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<AgeField, Self::Err> {
+        match s {
+            "updated" => Ok(AgeField::Updated),
+            "created" => Ok(AgeField::Created),
+            _ => Err("invalid age field"),
+        }
+    }
+}
+
+/// The retention rules used to select which tags of an image should be deleted
+///
+/// Only the tags whose name matches `tag_match`, and that were updated within `newer_than` if
+/// given, are considered at all. Of those, a tag is kept if
+/// it satisfies *any* of the retention rules, so only the tags that fail every rule are deleted.
+/// When no rules are given, every considered tag is selected for deletion.
+///
+/// Tags whose name matches `exclude` are never deleted, regardless of any other rule, and neither
+/// is the `latest` tag unless `protect_latest` is turned off. Finally, the `min_keep` newest tags
+/// of the image are never deleted, as a safety floor against emptying it.
+pub struct FilterOptions {
+    /// Never delete tags whose name matches any of these globs
+    pub exclude: Option<GlobSet>,
+    /// Never delete the tag named `latest`
+    pub protect_latest: bool,
+    /// Only consider tags whose name matches this pattern
+    pub tag_match: Option<Regex>,
+    /// Only consider tags that were updated within the given duration, to delete recently pushed
+    /// tags
+    pub newer_than: Option<Duration>,
+    /// Keep the `n` most recently updated tags
+    pub keep_last: Option<u64>,
+    /// Keep every tag pointing at one of the `n` most recently updated distinct digests
+    pub keep_last_digests: Option<u64>,
+    /// Keep the `n` most recently updated tags of each tag name, for registries that retain the
+    /// earlier digests of a re-pushed tag
+    pub keep_last_by_name: Option<u64>,
+    /// Keep the most recently updated tag of each of the `n` most recent days that have a tag
+    pub keep_daily: Option<u64>,
+    /// Keep the most recently updated tag of each of the `n` most recent ISO weeks that have a tag
+    pub keep_weekly: Option<u64>,
+    /// Keep the most recently updated tag of each of the `n` most recent months that have a tag
+    pub keep_monthly: Option<u64>,
+    /// Keep the tags named after the `n` highest semantic versions
+    ///
+    /// Tags whose name isn't a semantic version are kept as well, unless `prune_non_semver` is
+    /// true.
+    pub keep_last_semver: Option<u64>,
+    /// Let the other rules prune tags that aren't semantic versions when `keep_last_semver` is
+    /// given
+    pub prune_non_semver: bool,
+    /// Keep the tags that were updated within the given duration
+    pub keep_within: Option<Duration>,
+    /// Keep the tags that were updated at or after this time, so only older tags are pruned
+    pub older_than: Option<DateTime<Utc>>,
+    /// Keep the tags that were updated at or after the last tag pointing at this known-good
+    /// digest, so only the tags pushed before it are pruned
+    ///
+    /// Every tag is kept if no tag of the image has the digest.
+    pub since_digest: Option<String>,
+    /// Whether `keep_within` and `older_than` compare against the time a tag was updated or
+    /// created
+    pub age_by: AgeField,
+    /// Never delete the `n` most recently updated tags, regardless of any other rule
+    pub min_keep: u64,
+}
+
+impl Default for FilterOptions {
+    fn default() -> Self {
+        FilterOptions {
+            exclude: None,
+            protect_latest: true,
+            tag_match: None,
+            newer_than: None,
+            keep_last: None,
+            keep_last_digests: None,
+            keep_last_by_name: None,
+            keep_daily: None,
+            keep_weekly: None,
+            keep_monthly: None,
+            keep_last_semver: None,
+            prune_non_semver: false,
+            keep_within: None,
+            older_than: None,
+            since_digest: None,
+            age_by: AgeField::Updated,
+            min_keep: 0,
+        }
+    }
+}
+
+impl FilterOptions {
+    /// Returns a builder for filter options that validates the combination of rules
+    pub fn builder() -> FilterOptionsBuilder {
+        FilterOptionsBuilder::default()
+    }
+
+    /// Returns true if `tag` should be considered for deletion at all, relative to the time `now`
+    fn is_matching(&self, tag: &ImageTag, now: DateTime<Utc>) -> bool {
+        self.tag_match
+            .as_ref()
+            .is_none_or(|re| re.is_match(tag.name()))
+            && self.newer_than.is_none_or(|d| tag.is_newer_than(now - d))
+    }
+
+    /// Returns true if `tag` is protected from deletion
+    fn is_excluded(&self, tag: &ImageTag) -> bool {
+        (self.protect_latest && tag.name() == "latest")
+            || self
+                .exclude
+                .as_ref()
+                .is_some_and(|set| set.is_match(tag.name()))
+    }
+
+    /// Returns true if the tag at `index` in the newest-first list of tags is within the last
+    /// `keep_last` tags
+    fn is_within_last(&self, index: usize) -> bool {
+        self.keep_last.is_some_and(|n| (index as u64) < n)
+    }
+
+    /// Returns true if the digest at `rank` in the newest-first list of distinct digests is within
+    /// the last `keep_last_digests` digests
+    fn is_within_last_digests(&self, rank: usize) -> bool {
+        self.keep_last_digests.is_some_and(|n| (rank as u64) < n)
+    }
+
+    /// Returns true if the tag at `rank` in the newest-first list of tags with the same name is
+    /// within the last `keep_last_by_name` tags of that name
+    fn is_within_last_by_name(&self, rank: usize) -> bool {
+        self.keep_last_by_name.is_some_and(|n| (rank as u64) < n)
+    }
+
+    /// Returns true if `tag` is kept by `keep_last_semver`, given the lowest version that is
+    /// within the last `n` versions, or `None` if no version is
+    fn is_within_last_semver(&self, tag: &ImageTag, lowest_kept: Option<&Version>) -> bool {
+        if self.keep_last_semver.is_none() {
+            return false;
+        }
+
+        match parse_version(tag.name()) {
+            Some(version) => lowest_kept.is_some_and(|lowest| version >= *lowest),
+            None => !self.prune_non_semver,
+        }
+    }
+
+    /// Returns true if `tag` was updated, or created depending on `age_by`, within `keep_within`
+    /// of `now`
+    fn is_within_duration(&self, tag: &ImageTag, now: DateTime<Utc>) -> bool {
+        self.keep_within
+            .is_some_and(|d| self.timestamp(tag) >= now - d)
+    }
+
+    /// Returns true if `tag` was updated, or created depending on `age_by`, at or after
+    /// `older_than`
+    fn is_not_older_than(&self, tag: &ImageTag) -> bool {
+        self.older_than
+            .is_some_and(|date_time| self.timestamp(tag) >= date_time)
+    }
+
+    /// Returns true if `tag` was updated at or after the `cutoff` of `since_digest`, or if there
+    /// is no cutoff because the digest wasn't found
+    fn is_since_digest(&self, tag: &ImageTag, cutoff: Option<DateTime<Utc>>) -> bool {
+        self.since_digest.is_some() && cutoff.is_none_or(|cutoff| tag.updated_at() >= cutoff)
+    }
+
+    /// Returns the timestamp of `tag` that the time-based rules compare against
+    fn timestamp(&self, tag: &ImageTag) -> DateTime<Utc> {
+        match self.age_by {
+            AgeField::Updated => tag.updated_at(),
+            AgeField::Created => tag.created_at(),
+        }
+    }
+}
+
+/// A builder for [`FilterOptions`] that rejects dangerous combinations of rules on `build()`
+#[derive(Default)]
+pub struct FilterOptionsBuilder {
+    options: FilterOptions,
+}
+
+impl FilterOptionsBuilder {
+    /// Never delete tags whose name matches any of the globs in `set`
+    pub fn exclude(mut self, set: GlobSet) -> Self {
+        self.options.exclude = Some(set);
+        self
+    }
+
+    /// Sets whether the tag named `latest` is never deleted
+    pub fn protect_latest(mut self, protect: bool) -> Self {
+        self.options.protect_latest = protect;
+        self
+    }
+
+    /// Only consider tags whose name matches `re`
+    pub fn tag_match(mut self, re: Regex) -> Self {
+        self.options.tag_match = Some(re);
+        self
+    }
+
+    /// Only consider tags that were updated within `duration`
+    pub fn newer_than(mut self, duration: Duration) -> Self {
+        self.options.newer_than = Some(duration);
+        self
+    }
+
+    /// Keep the `n` most recently updated tags
+    pub fn keep_last(mut self, n: u64) -> Self {
+        self.options.keep_last = Some(n);
+        self
+    }
+
+    /// Keep every tag pointing at one of the `n` most recently updated distinct digests
+    pub fn keep_last_digests(mut self, n: u64) -> Self {
+        self.options.keep_last_digests = Some(n);
+        self
+    }
+
+    /// Keep the `n` most recently updated tags of each tag name
+    pub fn keep_last_by_name(mut self, n: u64) -> Self {
+        self.options.keep_last_by_name = Some(n);
+        self
+    }
+
+    /// Keep the most recently updated tag of each of the `n` most recent days
+    pub fn keep_daily(mut self, n: u64) -> Self {
+        self.options.keep_daily = Some(n);
+        self
+    }
+
+    /// Keep the most recently updated tag of each of the `n` most recent ISO weeks
+    pub fn keep_weekly(mut self, n: u64) -> Self {
+        self.options.keep_weekly = Some(n);
+        self
+    }
+
+    /// Keep the most recently updated tag of each of the `n` most recent months
+    pub fn keep_monthly(mut self, n: u64) -> Self {
+        self.options.keep_monthly = Some(n);
+        self
+    }
+
+    /// Keep the tags named after the `n` highest semantic versions
+    pub fn keep_last_semver(mut self, n: u64) -> Self {
+        self.options.keep_last_semver = Some(n);
+        self
+    }
+
+    /// Sets whether tags that aren't semantic versions can be pruned by the other rules
+    pub fn prune_non_semver(mut self, prune: bool) -> Self {
+        self.options.prune_non_semver = prune;
+        self
+    }
+
+    /// Keep the tags that were updated within `duration`
+    pub fn keep_within(mut self, duration: Duration) -> Self {
+        self.options.keep_within = Some(duration);
+        self
+    }
+
+    /// Keep the tags that were updated at or after `date_time`
+    pub fn older_than(mut self, date_time: DateTime<Utc>) -> Self {
+        self.options.older_than = Some(date_time);
+        self
+    }
+
+    /// Keep the tags that were updated at or after the last tag pointing at `digest`
+    pub fn since_digest(mut self, digest: &str) -> Self {
+        self.options.since_digest = Some(digest.to_owned());
+        self
+    }
+
+    /// Sets which timestamp of a tag the time-based rules compare against
+    pub fn age_by(mut self, field: AgeField) -> Self {
+        self.options.age_by = field;
+        self
+    }
+
+    /// Never delete the `n` most recently updated tags
+    pub fn min_keep(mut self, n: u64) -> Self {
+        self.options.min_keep = n;
+        self
+    }
+
+    /// Returns the filter options, or `Error::InvalidFilter` if the rules are contradictory or
+    /// given in a way that would delete every tag
+    pub fn build(self) -> Result<FilterOptions, Error> {
+        let options = self.options;
+
+        if options.prune_non_semver && options.keep_last_semver.is_none() {
+            return Err(Error::InvalidFilter(
+                "pruning tags that aren't semantic versions requires --keep-last-semver",
+            ));
+        }
+
+        if let (Some(newer_than), Some(keep_within)) = (options.newer_than, options.keep_within) {
+            if keep_within >= newer_than {
+                return Err(Error::InvalidFilter(
+                    "--keep-within keeps every tag selected by --newer-than",
+                ));
+            }
+        }
+
+        // Rules that are given but can't keep anything, e.g. `--keep-last 0`, are most likely a
+        // mistake rather than a way of deleting every tag
+        let has_rules = options.keep_last.is_some()
+            || options.keep_last_digests.is_some()
+            || options.keep_last_by_name.is_some()
+            || options.keep_daily.is_some()
+            || options.keep_weekly.is_some()
+            || options.keep_monthly.is_some()
+            || options.keep_last_semver.is_some()
+            || options.keep_within.is_some()
+            || options.older_than.is_some()
+            || options.since_digest.is_some();
+        let keeps_any = options.keep_last.is_some_and(|n| n > 0)
+            || options.keep_last_digests.is_some_and(|n| n > 0)
+            || options.keep_last_by_name.is_some_and(|n| n > 0)
+            || options.keep_daily.is_some_and(|n| n > 0)
+            || options.keep_weekly.is_some_and(|n| n > 0)
+            || options.keep_monthly.is_some_and(|n| n > 0)
+            || options
+                .keep_last_semver
+                .is_some_and(|n| n > 0 || !options.prune_non_semver)
+            || options.keep_within.is_some_and(|d| d > Duration::zero())
+            || options.older_than.is_some()
+            || options.since_digest.is_some();
+
+        if has_rules && !keeps_any && options.min_keep == 0 {
+            return Err(Error::InvalidFilter(
+                "the retention rules keep no tags and would delete every tag",
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the time the most recently updated tag pointing at `digest` was updated, or `None` if
+/// none of the `tags` has the digest
+pub fn digest_cutoff(tags: &[ImageTag], digest: &str) -> Option<DateTime<Utc>> {
+    tags.iter()
+        .filter(|tag| tag.digest() == digest)
+        .map(|tag| tag.updated_at())
+        .max()
+}
+
+/// A calendar period that the tiered retention rules keep the newest tag of
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Period {
+    Day,
+    /// An ISO week, starting on Monday
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Returns the year and the number of the period within the year that `date_time` falls in
+    fn bucket(self, date_time: DateTime<Utc>) -> (i32, u32) {
+        match self {
+            Period::Day => (date_time.year(), date_time.ordinal()),
+            Period::Week => {
+                let week = date_time.iso_week();
+
+                (week.year(), week.week())
+            }
+            Period::Month => (date_time.year(), date_time.month()),
+        }
+    }
+}
+
+/// Returns the IDs of the most recently updated tag in each of the `n` most recent periods that
+/// have a tag, as used by backup tools for grandfather-father-son retention
+///
+/// `tags` is expected to be sorted with the most recently updated tag first.
+fn newest_per_period<'a>(tags: &[&'a ImageTag], period: Period, n: u64) -> HashSet<&'a str> {
+    let mut kept = HashSet::new();
+    let mut last_bucket = None;
+
+    for tag in tags.iter() {
+        if kept.len() as u64 >= n {
+            break;
+        }
+
+        let bucket = period.bucket(tag.updated_at());
+
+        if last_bucket != Some(bucket) {
+            kept.insert(tag.id());
+            last_bucket = Some(bucket);
+        }
+    }
+
+    kept
+}
+
+/// Parses the name of a tag like `1.2.3` or `v1.2.3` as a semantic version
+fn parse_version(name: &str) -> Option<Version> {
+    Version::parse(name.strip_prefix('v').unwrap_or(name)).ok()
+}
+
+/// Returns the lowest of the `n` highest distinct versions among the names of `tags`
+fn lowest_kept_version(tags: &[&ImageTag], n: u64) -> Option<Version> {
+    let mut versions = tags
+        .iter()
+        .filter_map(|tag| parse_version(tag.name()))
+        .collect::<Vec<_>>();
+
+    versions.sort_by(|a, b| b.cmp(a));
+    versions.dedup();
+    versions.truncate(n as usize);
+    versions.pop()
+}
+
+/// Returns the tags in `image_tags` that should be deleted according to the retention rules in
+/// `filter`, relative to the time `now`
+///
+/// `image_tags` is expected to be sorted with the most recently updated tag first. Tags that don't
+/// match `tag_match` are left out before `keep_last` and `keep_last_digests` are counted.
+pub fn filter_image_tags<'a>(
+    filter: &FilterOptions,
+    image_tags: &'a [ImageTag],
+    now: DateTime<Utc>,
+) -> Vec<&'a ImageTag> {
+    let matching_tags = image_tags
+        .iter()
+        .filter(|tag| filter.is_matching(tag, now))
+        .collect::<Vec<&ImageTag>>();
+
+    // Rank the digests by the most recent tag pointing at them
+    let mut digest_ranks: HashMap<&str, usize> = HashMap::new();
+
+    for tag in matching_tags.iter() {
+        let rank = digest_ranks.len();
+
+        digest_ranks.entry(tag.digest()).or_insert(rank);
+    }
+
+    // Rank each tag among the tags with the same name, newest first
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    let name_ranks = matching_tags
+        .iter()
+        .map(|tag| {
+            let count = name_counts.entry(tag.name()).or_insert(0);
+            let rank = *count;
+
+            *count += 1;
+            rank
+        })
+        .collect::<Vec<usize>>();
+
+    let cutoff = filter
+        .since_digest
+        .as_deref()
+        .and_then(|digest| digest_cutoff(image_tags, digest));
+
+    // The tiered rules keep the union of the tags each of them keeps
+    let tiered = [
+        (Period::Day, filter.keep_daily),
+        (Period::Week, filter.keep_weekly),
+        (Period::Month, filter.keep_monthly),
+    ]
+    .iter()
+    .filter_map(|&(period, n)| n.map(|n| newest_per_period(&matching_tags, period, n)))
+    .flatten()
+    .collect::<HashSet<&str>>();
+
+    let lowest_kept = filter
+        .keep_last_semver
+        .and_then(|n| lowest_kept_version(&matching_tags, n));
+
+    // The safety floor counts every tag of the image, not just the matching ones
+    let newest_ids = image_tags
+        .iter()
+        .take(filter.min_keep as usize)
+        .map(|tag| tag.id())
+        .collect::<HashSet<&str>>();
+
+    matching_tags
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, tag)| {
+            let keep = filter.is_within_last(i)
+                || filter.is_within_last_digests(digest_ranks[tag.digest()])
+                || filter.is_within_last_by_name(name_ranks[i])
+                || tiered.contains(tag.id())
+                || filter.is_within_last_semver(tag, lowest_kept.as_ref())
+                || filter.is_within_duration(tag, now)
+                || filter.is_not_older_than(tag)
+                || filter.is_since_digest(tag, cutoff);
+
+            !keep
+        })
+        .map(|(_, tag)| tag)
+        .filter(|tag| !filter.is_excluded(tag))
+        .filter(|tag| !newest_ids.contains(tag.id()))
+        .collect::<Vec<&ImageTag>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{image_tag, image_tag_created_at, image_tag_with_digest};
+
+    use chrono::TimeZone;
+    use globset::{Glob, GlobSetBuilder};
+
+    /// Returns the names of the given `tags`
+    fn tag_names<'a>(tags: &[&'a ImageTag]) -> Vec<&'a str> {
+        tags.iter().map(|t| t.name()).collect()
+    }
+
+    /// Returns a list of tags named `d0`, `d1`, .. that were updated the given number of days
+    /// before `now`, newest first
+    fn tags_updated_days_ago(now: DateTime<Utc>, days: &[i64]) -> Vec<ImageTag> {
+        days.iter()
+            .map(|&d| image_tag(&format!("d{}", d), now - Duration::days(d)))
+            .collect()
+    }
+
+    #[test]
+    fn it_selects_every_tag_without_rules() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2]);
+        let filter = FilterOptions::default();
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["d0", "d1", "d2"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_last_n_tags() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2, 3]);
+        let filter = FilterOptions {
+            keep_last: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["d2", "d3"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_tags_updated_within_duration() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 4]);
+        let filter = FilterOptions {
+            keep_within: Some(Duration::days(3)),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["d4"]);
+    }
+
+    #[test]
+    fn it_keeps_tags_within_last_n_but_outside_duration() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[5, 6, 7]);
+        let filter = FilterOptions {
+            keep_last: Some(1),
+            keep_within: Some(Duration::days(3)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["d6", "d7"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_tags_within_duration_but_outside_last_n() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2, 5]);
+        let filter = FilterOptions {
+            keep_last: Some(1),
+            keep_within: Some(Duration::days(3)),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["d5"]);
+    }
+
+    #[test]
+    fn it_keeps_tags_updated_since_a_date() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2, 3]);
+        let filter = FilterOptions {
+            older_than: Some(now - Duration::days(2)),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["d3"]);
+    }
+
+    #[test]
+    fn it_compares_against_the_creation_time_when_asked_to() {
+        let now = Utc::now();
+        // `latest` was created long ago but has been re-pushed recently
+        let tags = vec![
+            image_tag_created_at("latest", now - Duration::days(30), now),
+            image_tag_created_at("1.0", now - Duration::days(2), now - Duration::days(2)),
+        ];
+        let mut filter = FilterOptions {
+            protect_latest: false,
+            keep_within: Some(Duration::days(7)),
+            ..Default::default()
+        };
+
+        assert!(filter_image_tags(&filter, &tags, now).is_empty());
+
+        filter.age_by = AgeField::Created;
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["latest"]
+        );
+
+        filter.keep_within = None;
+        filter.older_than = Some(now - Duration::days(7));
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["latest"]
+        );
+
+        filter.age_by = AgeField::Updated;
+
+        assert!(filter_image_tags(&filter, &tags, now).is_empty());
+    }
+
+    #[test]
+    fn it_keeps_min_keep_tags_when_every_tag_is_too_old() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[2, 3, 4, 5]);
+        let filter = FilterOptions {
+            keep_within: Some(Duration::days(1)),
+            min_keep: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["d4", "d5"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_all_tags_of_the_last_n_digests() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag_with_digest("latest", "sha256:c", now),
+            image_tag_with_digest("1.2", "sha256:c", now - Duration::days(1)),
+            image_tag_with_digest("1.1", "sha256:b", now - Duration::days(2)),
+            image_tag_with_digest("stable", "sha256:b", now - Duration::days(3)),
+            image_tag_with_digest("1.0", "sha256:a", now - Duration::days(4)),
+        ];
+        let filter = FilterOptions {
+            keep_last_digests: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["1.0"]);
+    }
+
+    #[test]
+    fn it_counts_digests_from_their_most_recent_tag() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag_with_digest("latest", "sha256:a", now),
+            image_tag_with_digest("1.1", "sha256:b", now - Duration::days(1)),
+            image_tag_with_digest("1.0", "sha256:a", now - Duration::days(2)),
+        ];
+        let filter = FilterOptions {
+            keep_last_digests: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["1.1"]);
+    }
+
+    #[test]
+    fn it_keeps_the_highest_semantic_versions() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("1.0.1", now),
+            image_tag("main", now - Duration::days(1)),
+            image_tag("v2.0.0", now - Duration::days(2)),
+            image_tag("1.10.0", now - Duration::days(3)),
+            image_tag("1.9.0", now - Duration::days(4)),
+        ];
+        let filter = FilterOptions {
+            keep_last_semver: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["1.0.1", "1.9.0"]
+        );
+    }
+
+    #[test]
+    fn it_prunes_non_semver_tags_when_opted_in() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("main", now),
+            image_tag("2.0.0", now - Duration::days(1)),
+            image_tag("1.0.0", now - Duration::days(2)),
+            image_tag("feature-x", now - Duration::days(3)),
+        ];
+        let filter = FilterOptions {
+            keep_last_semver: Some(1),
+            prune_non_semver: true,
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["1.0.0", "feature-x"]
+        );
+    }
+
+    #[test]
+    fn it_protects_latest_by_default() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("1.1", now),
+            image_tag("1.0", now - Duration::days(1)),
+            image_tag("latest", now - Duration::days(2)),
+        ];
+        let filter = FilterOptions {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(tag_names(&filter_image_tags(&filter, &tags, now)), ["1.0"]);
+    }
+
+    #[test]
+    fn it_selects_latest_when_not_protected() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("1.1", now),
+            image_tag("latest", now - Duration::days(2)),
+        ];
+        let filter = FilterOptions {
+            keep_last: Some(1),
+            protect_latest: false,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["latest"]
+        );
+    }
+
+    #[test]
+    fn it_only_selects_tags_matching_pattern() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("latest", now),
+            image_tag("pr-12", now - Duration::days(1)),
+            image_tag("1.0.0", now - Duration::days(2)),
+            image_tag("pr-11", now - Duration::days(3)),
+            image_tag("pr-10", now - Duration::days(4)),
+        ];
+        let filter = FilterOptions {
+            tag_match: Some(Regex::new(r"^pr-\d+$").unwrap()),
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["pr-11", "pr-10"]
+        );
+    }
+
+    #[test]
+    fn it_never_selects_excluded_tags() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("a1b2c3d", now),
+            image_tag("stable", now - Duration::days(1)),
+            image_tag("e4f5a6b", now - Duration::days(2)),
+            image_tag("prod-eu", now - Duration::days(3)),
+        ];
+        let mut exclude = GlobSetBuilder::new();
+        exclude.add(Glob::new("stable").unwrap());
+        exclude.add(Glob::new("prod-*").unwrap());
+        let filter = FilterOptions {
+            exclude: Some(exclude.build().unwrap()),
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["e4f5a6b"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_last_tags_of_each_name() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag_with_digest("stable", "sha256:d", now),
+            image_tag_with_digest("nightly", "sha256:c", now - Duration::days(1)),
+            image_tag_with_digest("stable", "sha256:b", now - Duration::days(2)),
+            image_tag_with_digest("nightly", "sha256:a", now - Duration::days(3)),
+            image_tag_with_digest("stable", "sha256:9", now - Duration::days(4)),
+            image_tag_with_digest("1.0", "sha256:8", now - Duration::days(5)),
+        ];
+        let filter = FilterOptions {
+            keep_last_by_name: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter_image_tags(&filter, &tags, now)
+                .iter()
+                .map(|t| t.digest())
+                .collect::<Vec<_>>(),
+            ["sha256:9"]
+        );
+
+        let filter = FilterOptions {
+            keep_last_by_name: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter_image_tags(&filter, &tags, now)
+                .iter()
+                .map(|t| t.digest())
+                .collect::<Vec<_>>(),
+            ["sha256:b", "sha256:a", "sha256:9"]
+        );
+    }
+
+    /// Returns tags spread over several months, newest first, where 2020-06-15 is a Monday
+    fn tags_spread_over_months() -> Vec<ImageTag> {
+        [
+            ("a", 2020, 6, 15, 10),
+            ("b", 2020, 6, 15, 8),
+            ("c", 2020, 6, 14, 12),
+            ("d", 2020, 6, 10, 12),
+            ("e", 2020, 6, 1, 12),
+            ("f", 2020, 5, 20, 12),
+            ("g", 2020, 5, 2, 12),
+            ("h", 2020, 4, 10, 12),
+            ("i", 2020, 3, 1, 12),
+        ]
+        .iter()
+        .map(|&(name, y, m, d, h)| image_tag(name, Utc.ymd(y, m, d).and_hms(h, 0, 0)))
+        .collect()
+    }
+
+    #[test]
+    fn it_keeps_the_newest_tag_of_each_day() {
+        let tags = tags_spread_over_months();
+        let now = Utc.ymd(2020, 6, 15).and_hms(12, 0, 0);
+        let filter = FilterOptions {
+            keep_daily: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["b", "d", "e", "f", "g", "h", "i"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_newest_tag_of_each_week() {
+        let tags = tags_spread_over_months();
+        let now = Utc.ymd(2020, 6, 15).and_hms(12, 0, 0);
+        let filter = FilterOptions {
+            keep_weekly: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["b", "d", "f", "g", "h", "i"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_newest_tag_of_each_month() {
+        let tags = tags_spread_over_months();
+        let now = Utc.ymd(2020, 6, 15).and_hms(12, 0, 0);
+        let filter = FilterOptions {
+            keep_monthly: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["b", "c", "d", "e", "g", "i"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_union_of_the_tiered_rules() {
+        let tags = tags_spread_over_months();
+        let now = Utc.ymd(2020, 6, 15).and_hms(12, 0, 0);
+        let filter = FilterOptions {
+            keep_daily: Some(2),
+            keep_weekly: Some(3),
+            keep_monthly: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["b", "d", "g", "i"]
+        );
+    }
+
+    #[test]
+    fn it_selects_tags_pushed_before_a_known_good_digest() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag_with_digest("2.1", "sha256:e", now),
+            image_tag_with_digest("stable", "sha256:d", now - Duration::days(1)),
+            image_tag_with_digest("2.0", "sha256:d", now - Duration::days(2)),
+            image_tag_with_digest("1.9", "sha256:c", now - Duration::days(1)),
+            image_tag_with_digest("1.8", "sha256:b", now - Duration::days(3)),
+        ];
+        let filter = FilterOptions {
+            since_digest: Some("sha256:d".to_owned()),
+            ..Default::default()
+        };
+
+        // The cutoff is the most recent tag with the digest, and tags updated at the same time
+        // are kept
+        assert_eq!(
+            digest_cutoff(&tags, "sha256:d"),
+            Some(now - Duration::days(1))
+        );
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["2.0", "1.8"]
+        );
+    }
+
+    #[test]
+    fn it_keeps_every_tag_when_the_known_good_digest_is_missing() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2]);
+        let filter = FilterOptions {
+            since_digest: Some("sha256:missing".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(digest_cutoff(&tags, "sha256:missing"), None);
+        assert!(filter_image_tags(&filter, &tags, now).is_empty());
+    }
+
+    #[test]
+    fn it_only_selects_tags_newer_than_the_window() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("broken", now - Duration::minutes(10)),
+            image_tag("also-broken", now - Duration::minutes(50)),
+            image_tag("good", now - Duration::hours(2)),
+            image_tag("old", now - Duration::days(3)),
+        ];
+        let filter = FilterOptions {
+            newer_than: Some(Duration::hours(1)),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["broken", "also-broken"]
+        );
+    }
+
+    #[test]
+    fn it_applies_retention_rules_within_the_newer_than_window() {
+        let now = Utc::now();
+        let tags = tags_updated_days_ago(now, &[0, 1, 2, 5]);
+        let filter = FilterOptions {
+            newer_than: Some(Duration::days(3)),
+            keep_last: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tag_names(&filter_image_tags(&filter, &tags, now)),
+            ["d1", "d2"]
+        );
+    }
+
+    #[test]
+    fn it_rejects_keep_within_covering_newer_than() {
+        assert!(matches!(
+            FilterOptions::builder()
+                .newer_than(Duration::hours(1))
+                .keep_within(Duration::days(1))
+                .build(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn it_builds_filter_options() {
+        let filter = FilterOptions::builder()
+            .keep_last(3)
+            .keep_within(Duration::days(7))
+            .protect_latest(false)
+            .age_by(AgeField::Created)
+            .build()
+            .unwrap();
+
+        assert_eq!(filter.keep_last, Some(3));
+        assert_eq!(filter.keep_within, Some(Duration::days(7)));
+        assert!(!filter.protect_latest);
+        assert_eq!(filter.age_by, AgeField::Created);
+    }
+
+    #[test]
+    fn it_rejects_rules_that_keep_nothing() {
+        assert!(matches!(
+            FilterOptions::builder().keep_last(0).build(),
+            Err(Error::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            FilterOptions::builder()
+                .keep_last(0)
+                .keep_last_digests(0)
+                .keep_within(Duration::zero())
+                .build(),
+            Err(Error::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            FilterOptions::builder()
+                .keep_last_semver(0)
+                .prune_non_semver(true)
+                .build(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn it_accepts_rules_that_keep_nothing_alongside_other_rules() {
+        assert!(FilterOptions::builder()
+            .keep_last(0)
+            .keep_within(Duration::days(1))
+            .build()
+            .is_ok());
+        assert!(FilterOptions::builder()
+            .keep_last(0)
+            .min_keep(2)
+            .build()
+            .is_ok());
+        assert!(FilterOptions::builder().keep_last_semver(0).build().is_ok());
+        assert!(FilterOptions::builder().build().is_ok());
+    }
+
+    #[test]
+    fn it_rejects_prune_non_semver_without_keep_last_semver() {
+        assert!(matches!(
+            FilterOptions::builder().prune_non_semver(true).build(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+}
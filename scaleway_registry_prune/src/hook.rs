@@ -0,0 +1,44 @@
+use std::io;
+use std::process::Stdio;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::error::Error;
+use crate::plan_file::PlanFile;
+
+/// Runs the shell `command` with the deletion `plan` as JSON on its stdin
+///
+/// The output of the command goes to stderr, so it doesn't mix with the printed plan.
+///
+/// Fails if the command can't be run or doesn't exit successfully, in which case nothing must be
+/// deleted.
+pub async fn run_pre_delete_hook(command: &str, plan: &PlanFile) -> Result<(), Error> {
+    let json = serde_json::to_vec(plan).expect("plan can't be serialized");
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::from(io::stderr()))
+        .spawn()
+        .map_err(Error::PreDeleteHookError)?;
+    let mut stdin = child.stdin.take().unwrap();
+
+    // A hook that doesn't care about the plan may exit without reading it
+    match stdin.write_all(&json).await {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            // Don't leave the hook running, or around as a zombie
+            let _ = child.kill().await;
+
+            return Err(Error::PreDeleteHookError(err));
+        }
+        _ => drop(stdin),
+    }
+
+    let status = child.wait().await.map_err(Error::PreDeleteHookError)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::PreDeleteHookFailed(status))
+    }
+}
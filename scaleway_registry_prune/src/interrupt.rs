@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The exit code of a run that was interrupted with Ctrl-C, as is customary for SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// A flag that is set once the run is interrupted, to stop starting new deletions
+///
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    interrupted: Arc<AtomicBool>,
+    /// Whether Ctrl-C exits right away rather than setting the flag
+    disarmed: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    /// Returns true if the run has been interrupted
+    pub fn is_set(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Marks the run as interrupted
+    pub fn set(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    /// Makes Ctrl-C exit right away again, once there are no deletions left to finish
+    pub fn disarm(&self) {
+        self.disarmed.store(true, Ordering::SeqCst);
+    }
+
+    /// Sets the flag when Ctrl-C is pressed instead of exiting right away, and exits when it's
+    /// pressed a second time or after `disarm`
+    pub fn listen_for_ctrl_c(&self) {
+        let interrupt = self.clone();
+
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if interrupt.disarmed.load(Ordering::SeqCst) || interrupt.is_set() {
+                    std::process::exit(INTERRUPTED_EXIT_CODE);
+                }
+
+                eprintln!("Interrupted, waiting for the ongoing deletions to finish");
+                interrupt.set();
+            }
+        });
+    }
+}
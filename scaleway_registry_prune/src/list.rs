@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+
+use scaleway_sdk::registry::{Image, ImageTag};
+
+use crate::format_age;
+use crate::table::{format_date_time, format_table, short_digest};
+
+/// The headers of the columns in the tag table
+const HEADERS: [&str; 6] = ["TAG", "DIGEST", "STATUS", "CREATED", "UPDATED", "AGE"];
+
+/// Returns an aligned table of the given `tags` and the image they belong to, with their age
+/// relative to `now`
+pub fn format_tag_table(tags: &[(&Image, &ImageTag)], now: DateTime<Utc>) -> String {
+    let rows = tags
+        .iter()
+        .map(|(image, tag)| {
+            [
+                format!("{}:{}", image.name(), tag.name()),
+                short_digest(tag).to_owned(),
+                tag.status().to_string(),
+                format_date_time(tag.created_at()),
+                format_date_time(tag.updated_at()),
+                format_age(tag.age_at(now)),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    format_table(HEADERS, &rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+
+    #[test]
+    fn it_formats_an_aligned_tag_table() {
+        let list: serde_json::Value = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/image_tag_list_page_2.json"
+        ))
+        .unwrap();
+        let tags = list["tags"].as_array().unwrap()[..2]
+            .iter()
+            .map(|tag| serde_json::from_str::<ImageTag>(&tag.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        let image = test_helpers::image("myimage");
+        let rows = tags.iter().map(|t| (&image, t)).collect::<Vec<_>>();
+        let now = tags[0].updated_at() + chrono::Duration::days(3);
+        let table = format_tag_table(&rows, now);
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("TAG "));
+        assert!(lines[1].starts_with(&format!("myimage:{} ", tags[0].name())));
+        assert!(lines[1].contains(&format!(" {} ", short_digest(&tags[0]))));
+        assert!(lines[1].ends_with("3 days old"));
+
+        // Every column starts at the same offset on every line
+        let offset = lines[0].find("DIGEST").unwrap();
+
+        assert!(lines[1..]
+            .iter()
+            .all(|line| line[offset..].starts_with("sha256:")));
+    }
+}
@@ -1,56 +1,504 @@
-use std::fmt::Display;
-use std::io::{self, Write};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::env;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Instant;
 
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
 use clap::{crate_authors, crate_name, crate_version, App, Arg, ArgMatches};
+use console::{style, StyledObject};
+use dialoguer::MultiSelect;
+use futures::stream::{self, StreamExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
+use log::debug;
+use regex::Regex;
+use serde::Serialize;
 
 use scaleway_sdk::{
-    registry::{Image, ImageTag, Namespace},
-    Registry,
+    registry::{Image, ImageTag, Namespace, Status, REGIONS},
+    Error as ScalewaySdkError, Registry,
 };
 
+mod cache;
+use cache::{Cache, Listing};
+
+mod config;
+use config::{Config, Profile};
 mod error;
 use error::Error;
+mod filter;
+mod hook;
+mod interrupt;
+use filter::{digest_cutoff, filter_image_tags, FilterOptions};
+use hook::run_pre_delete_hook;
+use interrupt::Interrupt;
+
+mod list;
+use list::format_tag_table;
+mod metrics;
+use metrics::Metrics;
+
+mod plan;
+mod plan_file;
+use plan_file::PlanFile;
+mod rate_limit;
+use rate_limit::RateLimiter;
+mod report;
+use report::{PruneReport, TagRef};
+mod resume;
+use resume::ResumeLog;
+
+mod stats;
+use stats::Stats;
+mod table;
+use plan::{index_digests, plan_deletions, Deletion, DeletionPlan, SkipReason};
+#[cfg(test)]
+mod test_helpers;
+
+/// The known regions where the registry may not be fully available, along with why
+const LIMITED_REGIONS: &[(&str, &str)] = &[(
+    "pl-waw",
+    "the registry was launched there after the other regions and may not be enabled for every \
+     account",
+)];
+
+/// The age in days above which a tag is highlighted in the deletion plan
+const OLD_TAG_AGE_DAYS: i64 = 90;
+
+/// The template of the deletion progress bar
+const PROGRESS_TEMPLATE: &str = "{prefix} {wide_bar} {pos}/{len} ({per_sec}, {eta} left)";
+
+/// The interval between the checks of whether deleted tags are gone with `--wait`
+const WAIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The template of the spinner shown while listing namespaces and images
+const SPINNER_TEMPLATE: &str = "{spinner} {msg}";
+
+/// The format used when printing the deletion plan
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// Human-readable lines of text
+    Text,
+    /// A JSON array of `PlannedDeletion` objects
+    Json,
+    /// A human-readable table with aligned columns
+    Table,
+    /// CSV with a header row, for loading the plan into spreadsheets
+    Csv,
+}
+
+impl OutputFormat {
+    /// Returns true if the output is meant to be read by other programs, so nothing else may be
+    /// written alongside it
+    fn is_machine_readable(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Csv)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<OutputFormat, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err("invalid output format"),
+        }
+    }
+}
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorChoice {
+    /// Only when writing to a terminal and `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns true if colors are used when writing to a stream that `is_terminal`, with
+    /// `no_color` being true if `NO_COLOR` is set
+    fn is_enabled(self, is_terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<ColorChoice, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err("invalid color choice"),
+        }
+    }
+}
+
+/// The field used to order the tags in the deletion plan
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortField {
+    /// The time the tag was last updated, newest first
+    Updated,
+    /// The time the tag was created, newest first
+    Created,
+    /// The name of the tag, in alphabetical order
+    Name,
+}
+
+impl FromStr for SortField {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<SortField, Self::Err> {
+        match s {
+            "updated" => Ok(SortField::Updated),
+            "created" => Ok(SortField::Created),
+            "name" => Ok(SortField::Name),
+            _ => Err("invalid sort field"),
+        }
+    }
+}
+
+/// The visibility of an image, once an image inheriting it from its namespace is resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Visibility {
+    Public,
+    Private,
+}
+
+impl FromStr for Visibility {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Visibility, Self::Err> {
+        match s {
+            "public" => Ok(Visibility::Public),
+            "private" => Ok(Visibility::Private),
+            _ => Err("invalid visibility"),
+        }
+    }
+}
+
+/// Returns the visibility of `image`, which inherits the visibility of its `namespace` unless
+/// it's set explicitly
+fn image_visibility(image: &Image, namespace: &Namespace) -> Visibility {
+    match image.visibility() {
+        "public" => Visibility::Public,
+        "private" => Visibility::Private,
+        _ if namespace.is_public() => Visibility::Public,
+        _ => Visibility::Private,
+    }
+}
 
-#[derive(Default)]
-struct FilterOptions {
-    keep_last: Option<u64>,
+/// A single tag in the deletion plan, as it is serialized in the JSON output
+#[derive(Serialize, Debug)]
+struct PlannedDeletion<'a> {
+    image: &'a str,
+    tag: &'a str,
+    digest: &'a str,
+    updated_at: DateTime<Utc>,
+    id: &'a str,
+}
+
+impl<'a> PlannedDeletion<'a> {
+    fn new(image: &'a Image, tag: &'a ImageTag) -> Self {
+        PlannedDeletion {
+            image: image.name(),
+            tag: tag.name(),
+            digest: tag.digest(),
+            updated_at: tag.updated_at(),
+            id: tag.id(),
+        }
+    }
+}
+
+/// A single `NAMESPACE[/IMAGE]` argument naming the images to prune
+#[derive(Debug, PartialEq)]
+struct Target {
+    namespace: String,
+    /// The name of the image, or `None` for every image in the namespace
+    image: Option<String>,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.image {
+            Some(ref image) => write!(f, "{}/{}", self.namespace, image),
+            None => write!(f, "{}", self.namespace),
+        }
+    }
+}
+
+/// The tags of a single image, along with the deletions planned for them
+struct ImagePlan<'a> {
+    image: &'a Image,
+    tags: &'a [ImageTag],
+    plan: DeletionPlan<'a>,
 }
 
 struct Options {
     token: String,
     region: String,
-    image: String,
-    namespace: String,
+    /// The API endpoint to use instead of the regional endpoint
+    endpoint: Option<String>,
+    /// The proxy to send every request through
+    proxy: Option<String>,
+    targets: Vec<Target>,
+    dry_run: bool,
+    /// Only print how many tags would be deleted and exit
+    count_only: bool,
+    list: bool,
+    /// Print aggregate numbers about the images instead of pruning them
+    stats: bool,
+    /// The names of the tags to delete regardless of the retention rules
+    tags: Vec<String>,
+    /// Let the user pick the tags to delete, starting from the tags selected by the filters
+    interactive: bool,
+    assume_yes: bool,
+    /// Only print errors
+    quiet: bool,
+    output: OutputFormat,
+    color: ColorChoice,
+    sort: SortField,
+    reverse: bool,
+    concurrency: usize,
+    /// The maximum number of images whose tags are listed at once
+    image_concurrency: usize,
+    force_shared_digests: bool,
+    /// Skip the deletion of digests that are referenced by other images of the namespace
+    protect_digests_in_use: bool,
+    /// Only prune the images of a namespace with this visibility
+    visibility: Option<Visibility>,
+    /// Check that the deleted tags are gone afterwards
+    verify: bool,
+    /// Wait for the deleted tags to be gone before reporting them as deleted
+    wait: bool,
+    /// The maximum time to wait for the deleted tags to be gone
+    wait_timeout: Duration,
+    /// Delete the images that have no tags left after pruning
+    delete_empty_images: bool,
+    /// The number of tags above which the name of what is pruned must be typed to confirm
+    confirm_threshold: usize,
+    /// The maximum number of delete requests started per second
+    max_rps: Option<NonZeroU32>,
+    /// The file to write the deletion plan to instead of deleting anything
+    plan_file: Option<PathBuf>,
+    /// The previously written deletion plan to carry out instead of applying any rules
+    apply_plan: Option<PlanFile>,
+    /// The previously written deletion plan to show the changes to the deletion plan since
+    diff_against: Option<PlanFile>,
+    /// The file to write Prometheus metrics about the run to
+    metrics_file: Option<PathBuf>,
+    /// The shell command to run with the deletion plan before deleting anything
+    pre_delete_hook: Option<String>,
+    /// The file logging the IDs of the deleted tags, to resume an interrupted run from
+    resume_log: Option<PathBuf>,
+    /// The file to cache the namespace and image listings in
+    cache: Option<PathBuf>,
+    /// The maximum age of a cached listing before it's fetched again
+    cache_ttl: Duration,
     filter: FilterOptions,
 }
 
-/// Takes a string in the format `<namespace>/<image>` and returns an Option
-/// with a tuple in the format `(namespace, image)` unleess the input string is
-/// malformed
-fn parse_image_argument(arg: &str) -> Option<(&str, &str)> {
+impl Target {
+    /// Returns the target named by `arg` or `None` if it is malformed, see
+    /// `parse_image_argument`
+    fn parse(arg: &str) -> Option<Target> {
+        parse_image_argument(arg).map(|(namespace, image)| Target {
+            namespace: namespace.to_string(),
+            image: image.map(str::to_string),
+        })
+    }
+}
+
+/// Reads a list of targets from `reader`, one `NAMESPACE[/IMAGE]` per line
+///
+/// Blank lines and lines starting with `#` are skipped.
+fn read_targets(reader: impl BufRead) -> Result<Vec<Target>, Error> {
+    let mut targets = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(Error::TargetsReadError)?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let target = Target::parse(line).ok_or_else(|| Error::MalformedTarget {
+            line: i + 1,
+            content: line.to_string(),
+        })?;
+
+        targets.push(target);
+    }
+
+    Ok(targets)
+}
+
+/// Reads the list of targets from the file at `path`, or from stdin if `path` is `-`
+fn read_targets_from(path: &str) -> Result<Vec<Target>, Error> {
+    if path == "-" {
+        return read_targets(io::stdin().lock());
+    }
+
+    let file = std::fs::File::open(path).map_err(Error::TargetsReadError)?;
+
+    read_targets(BufReader::new(file))
+}
+
+/// Takes a string in the format `<namespace>/<image>` or just `<namespace>` and returns an
+/// Option with a tuple in the format `(namespace, image)` unless the input string is malformed
+///
+/// The image is `None` when only a namespace is given, meaning every image in the namespace.
+/// Everything after the first `/` is the image, so nested image names like `ns/team/app` are
+/// supported, as long as none of the segments is empty.
+fn parse_image_argument(arg: &str) -> Option<(&str, Option<&str>)> {
     let mut parts = arg.splitn(2, '/');
 
     match (parts.next(), parts.next()) {
-        (Some(namespace), Some(image)) => {
-            if namespace.is_empty() || image.is_empty() {
-                None
-            } else {
-                Some((namespace, image))
-            }
-        }
-        _ => None,
+        (Some(""), _) | (None, _) => None,
+        (_, Some(image)) if image.split('/').any(str::is_empty) => None,
+        (Some(namespace), image) => Some((namespace, image)),
     }
 }
 
-/// Validates that the given `namespace/image` string can be parsed by `parse_image_argument`
+/// Validates that the given `namespace/image` or `namespace` string can be parsed by
+/// `parse_image_argument`
 ///
 /// This is used by `clap` when parsing arguments
 fn validate_image_arg(arg: String) -> Result<(), String> {
     parse_image_argument(&arg)
-        .ok_or_else(|| "Must be specified in the format `<namespace>/<image>'".to_owned())
+        .ok_or_else(|| {
+            "Must be specified in the format `<namespace>/<image>' or `<namespace>'".to_owned()
+        })
+        .map(|_| ())
+}
+
+/// Validates that the given endpoint is an HTTP(S) URL
+fn validate_endpoint(arg: String) -> Result<(), String> {
+    if arg.starts_with("http://") || arg.starts_with("https://") {
+        Ok(())
+    } else {
+        Err("The endpoint must be an http:// or https:// URL".to_owned())
+    }
+}
+
+/// Returns why the registry may not be available in `region`, or `None` if it's not known to be
+/// limited
+fn region_limitation(region: &str) -> Option<&'static str> {
+    LIMITED_REGIONS
+        .iter()
+        .find(|(name, _)| *name == region)
+        .map(|(_, reason)| *reason)
+}
+
+/// Validates that the given region is one of the `REGIONS` of the registry, suggesting the closest known
+/// region when it isn't
+///
+/// This is used by `clap` when parsing arguments
+fn validate_region(arg: String) -> Result<(), String> {
+    if REGIONS.contains(&arg.as_str()) {
+        return Ok(());
+    }
+
+    let suggestion = REGIONS
+        .iter()
+        .map(|region| (strsim::levenshtein(&arg, region), region))
+        .filter(|&(distance, _)| distance <= 2)
+        .min()
+        .map(|(_, region)| format!(", did you mean `{}'?", region))
+        .unwrap_or_default();
+
+    Err(format!(
+        "Unknown region, expected one of {}{}",
+        REGIONS.join(", "),
+        suggestion
+    ))
+}
+
+/// Returns true if the region or credentials aren't given as arguments, so they have to be read
+/// from the Scaleway config file
+fn needs_config(args: &ArgMatches) -> bool {
+    !args.is_present("region") || !(args.is_present("token") || args.is_present("secret-key"))
+}
+
+/// Parses a duration string like `90m`, `3d` or `2w` into a `chrono::Duration`
+///
+/// The supported suffixes are `s`, `m`, `h`, `d` and `w`
+fn parse_duration(arg: &str) -> Result<Duration, String> {
+    let duration = humantime::parse_duration(arg).map_err(|e| e.to_string())?;
+
+    Duration::from_std(duration).map_err(|e| e.to_string())
+}
+
+/// Parses the given RFC 3339 timestamp, or a bare `YYYY-MM-DD` date as midnight UTC
+fn parse_date_time(arg: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(arg) {
+        return Ok(date_time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(arg, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date_time| DateTime::from_utc(date_time, Utc))
+        .ok_or_else(|| "Expected an RFC 3339 timestamp or a YYYY-MM-DD date".to_owned())
+}
+
+/// Validates that the given string can be parsed by `parse_date_time`
+///
+/// This is used by `clap` when parsing arguments
+fn validate_date_time(arg: String) -> Result<(), String> {
+    parse_date_time(&arg).map(|_| ())
+}
+
+/// Validates that the given duration string can be parsed by `parse_duration`
+///
+/// This is used by `clap` when parsing arguments
+fn validate_duration(arg: String) -> Result<(), String> {
+    parse_duration(&arg).map(|_| ())
+}
+
+/// Validates that the given string is a valid regular expression
+///
+/// This is used by `clap` when parsing arguments
+fn validate_regex(arg: String) -> Result<(), String> {
+    Regex::new(&arg)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid regular expression: {}", e))
+}
+
+/// Validates that the given string is a valid glob pattern
+///
+/// This is used by `clap` when parsing arguments
+fn validate_glob(arg: String) -> Result<(), String> {
+    Glob::new(&arg)
         .map(|_| ())
+        .map_err(|e| format!("Invalid glob: {}", e))
+}
+
+/// Compiles the given glob `patterns` into a single `GlobSet`
+fn build_glob_set<'a>(patterns: impl Iterator<Item = &'a str>) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+
+    builder.build()
 }
 
 fn validate_parsable<T>(arg: String) -> Result<(), String>
@@ -64,64 +512,232 @@ where
         .map_err(|e| e.to_string())
 }
 
-/// Attempts to retrieve information about the given `image` and checks if it's
-/// part of the given `namespace` before returning both, unless an error occurs
-async fn get_namespace_and_image(
-    registry: &Registry,
+/// Finds the given `image` in the list of `images` and checks if it's part of the given
+/// `namespace` before returning both, unless an error occurs
+///
+/// The `namespace` is matched against the names of the namespaces first, falling back to their
+/// IDs. When no `image` is given, every image in the `namespace` is returned instead
+fn find_namespace_and_images<'a>(
+    namespaces: &'a [Namespace],
+    images: &'a [Image],
     namespace: &str,
-    image: &str,
-) -> Result<(Namespace, Image), Error> {
-    let namespace_vec = registry.namespaces().await?;
-    let namespace = namespace_vec
+    image: Option<&str>,
+) -> Result<(&'a Namespace, Vec<&'a Image>), Error> {
+    let namespace = namespaces
         .iter()
         .find(|ns| ns.name() == namespace)
-        .ok_or_else(|| Error::NoSuchNamespace)?;
+        .or_else(|| namespaces.iter().find(|ns| ns.id() == namespace))
+        .ok_or(Error::NoSuchNamespace)?;
 
-    let image_vec = registry.images().await?;
-    let image = image_vec
+    let images = images
         .iter()
         .filter(|x| x.namespace_id() == namespace.id())
-        .find(|x| x.name() == image)
-        .ok_or_else(|| Error::NoSuchImage)?;
+        .filter(|x| image.is_none_or(|name| x.name() == name))
+        .collect::<Vec<_>>();
+
+    if image.is_some() && images.is_empty() {
+        return Err(Error::NoSuchImage);
+    }
+
+    Ok((namespace, images))
+}
+
+/// Returns the namespaces and images in the region of the `registry`
+///
+/// When a `cache` file is given, a listing with the same `cache_key` that is younger than `ttl` is
+/// read from it instead of the API, and a fresh listing is written back to it.
+async fn list_namespaces_and_images(
+    registry: &Registry,
+    cache: Option<&Path>,
+    cache_key: &str,
+    ttl: Duration,
+) -> Result<Listing, Error> {
+    let now = Utc::now();
+    let mut cached = cache.map(Cache::load);
+
+    if let Some(listing) = cached.as_ref().and_then(|c| c.get(cache_key, ttl, now)) {
+        return Ok(listing.clone());
+    }
 
-    Ok((namespace.clone(), image.clone()))
+    let listing = Listing {
+        fetched_at: now,
+        namespaces: registry.namespaces().await?,
+        images: registry.images().await?,
+    };
+
+    if let (Some(path), Some(cached)) = (cache, cached.as_mut()) {
+        cached.insert(cache_key, listing.clone());
+
+        if let Err(err) = cached.save(path) {
+            log::warn!("Unable to write cache file {}: {}", path.display(), err);
+        }
+    }
+
+    Ok(listing)
 }
 
 /// Parses the `args` and returns an `Options` struct with the relevant fields set based on the
 /// given args
-fn parse_args(args: ArgMatches) -> Options {
-    let (namespace, image) = parse_image_argument(args.value_of("IMAGE").unwrap()).unwrap();
+///
+/// The region and credentials fall back to the values in the config file `profile` when they're
+/// given neither as arguments nor environment variables
+fn parse_args(args: ArgMatches, profile: &Profile) -> Result<Options, Error> {
+    let mut targets = args
+        .values_of("IMAGE")
+        .into_iter()
+        .flatten()
+        .map(|arg| Target::parse(arg).unwrap())
+        .collect::<Vec<_>>();
 
-    let keep_last = args
-        .value_of("keep-last")
-        .map(|s| s.parse::<u64>().unwrap());
+    if let Some(path) = args.value_of("from-file") {
+        targets.extend(read_targets_from(path)?);
+    }
 
-    let filter = FilterOptions { keep_last };
+    let apply_plan = args
+        .value_of("apply-plan")
+        .map(|path| PlanFile::load(Path::new(path)))
+        .transpose()
+        .map_err(Error::PlanFileReadError)?;
 
-    Options {
-        region: args.value_of("region").expect("missing region").to_string(),
-        token: args.value_of("token").expect("missing token").to_string(),
-        image: image.to_string(),
-        namespace: namespace.to_string(),
-        filter,
+    let diff_against = args
+        .value_of("diff-against")
+        .map(|path| PlanFile::load(Path::new(path)))
+        .transpose()
+        .map_err(Error::PlanFileReadError)?;
+
+    let mut filter = FilterOptions::builder()
+        .protect_latest(!args.is_present("no-protect-latest"))
+        .prune_non_semver(args.is_present("prune-non-semver"))
+        .age_by(args.value_of("by").unwrap().parse().unwrap());
+
+    if let Some(s) = args.value_of("keep-last") {
+        filter = filter.keep_last(s.parse().unwrap());
     }
-}
 
-fn filter_image_tags<'a>(options: &Options, image_tags: &'a [ImageTag]) -> Vec<&'a ImageTag> {
-    let filter = &options.filter;
+    if let Some(s) = args.value_of("keep-last-digests") {
+        filter = filter.keep_last_digests(s.parse().unwrap());
+    }
 
-    image_tags
-        .iter()
-        .enumerate()
-        .filter(|&(i, _x)| {
-            if let Some(n) = filter.keep_last {
-                (i as u64) > n
-            } else {
-                true
-            }
-        })
-        .map(|(_, x)| x)
-        .collect::<Vec<&ImageTag>>()
+    if let Some(s) = args.value_of("keep-last-by-name") {
+        filter = filter.keep_last_by_name(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("keep-daily") {
+        filter = filter.keep_daily(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("keep-weekly") {
+        filter = filter.keep_weekly(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("keep-monthly") {
+        filter = filter.keep_monthly(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("keep-last-semver") {
+        filter = filter.keep_last_semver(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("keep-within") {
+        filter = filter.keep_within(parse_duration(s).unwrap());
+    }
+
+    if let Some(s) = args.value_of("newer-than") {
+        filter = filter.newer_than(parse_duration(s).unwrap());
+    }
+
+    if let Some(s) = args.value_of("older-than") {
+        filter = filter.older_than(parse_date_time(s).unwrap());
+    }
+
+    if let Some(digest) = args.value_of("since-digest") {
+        filter = filter.since_digest(digest);
+    }
+
+    if let Some(s) = args.value_of("min-keep") {
+        filter = filter.min_keep(s.parse().unwrap());
+    }
+
+    if let Some(s) = args.value_of("match") {
+        filter = filter.tag_match(Regex::new(s).unwrap());
+    }
+
+    if let Some(values) = args.values_of("exclude") {
+        filter = filter.exclude(build_glob_set(values).unwrap());
+    }
+
+    let filter = filter.build()?;
+
+    let region = match args.value_of("region") {
+        Some(region) => region,
+        None => {
+            let region = profile
+                .default_region
+                .as_deref()
+                .ok_or(Error::MissingRegion)?;
+
+            validate_region(region.to_owned()).map_err(Error::InvalidConfigRegion)?;
+            region
+        }
+    };
+
+    let token = args
+        .value_of("token")
+        .or_else(|| args.value_of("secret-key"))
+        .or(profile.secret_key.as_deref())
+        .ok_or(Error::MissingCredentials)?;
+
+    Ok(Options {
+        region: region.to_string(),
+        endpoint: args.value_of("endpoint").map(str::to_owned),
+        proxy: args.value_of("proxy").map(str::to_owned),
+        token: token.to_string(),
+        targets,
+        dry_run: args.is_present("dry-run"),
+        count_only: args.is_present("count-only"),
+        list: args.is_present("list"),
+        stats: args.is_present("stats"),
+        interactive: args.is_present("interactive"),
+        tags: args
+            .values_of("tag")
+            .map_or_else(Vec::new, |values| values.map(str::to_owned).collect()),
+        assume_yes: args.is_present("yes"),
+        quiet: args.is_present("quiet"),
+        output: args.value_of("output").unwrap().parse().unwrap(),
+        color: args.value_of("color").unwrap().parse().unwrap(),
+        sort: args.value_of("sort").unwrap().parse().unwrap(),
+        reverse: args.is_present("reverse"),
+        concurrency: args
+            .value_of("concurrency")
+            .unwrap()
+            .parse::<NonZeroUsize>()
+            .unwrap()
+            .get(),
+        image_concurrency: args
+            .value_of("image-concurrency")
+            .unwrap()
+            .parse::<NonZeroUsize>()
+            .unwrap()
+            .get(),
+        force_shared_digests: args.is_present("force-shared-digests"),
+        protect_digests_in_use: args.is_present("protect-tags-with-digest-in-use"),
+        visibility: args.value_of("visibility").map(|s| s.parse().unwrap()),
+        verify: args.is_present("verify"),
+        wait: args.is_present("wait"),
+        wait_timeout: parse_duration(args.value_of("wait-timeout").unwrap()).unwrap(),
+        delete_empty_images: args.is_present("delete-empty-images"),
+        confirm_threshold: args.value_of("confirm-threshold").unwrap().parse().unwrap(),
+        max_rps: args.value_of("max-rps").map(|s| s.parse().unwrap()),
+        plan_file: args.value_of("plan-file").map(PathBuf::from),
+        metrics_file: args.value_of("metrics-file").map(PathBuf::from),
+        pre_delete_hook: args.value_of("pre-delete-hook").map(str::to_owned),
+        apply_plan,
+        diff_against,
+        resume_log: args.value_of("resume-log").map(PathBuf::from),
+        cache: args.value_of("cache").map(PathBuf::from),
+        cache_ttl: parse_duration(args.value_of("cache-ttl").unwrap()).unwrap(),
+        filter,
+    })
 }
 
 fn read_answer_from_stdin() -> io::Result<String> {
@@ -133,126 +749,2656 @@ fn read_answer_from_stdin() -> io::Result<String> {
     Ok(answer)
 }
 
-async fn try_main() -> Result<(), Error> {
-    env_logger::init();
+/// Returns true if the deletion has been confirmed, either up front with `assume_yes` or by
+/// answering the interactive prompt
+///
+/// When `expected` is given, the prompt asks for it to be typed out in full rather than for a
+/// `y`. If the prompt can't be shown because stdin is not `interactive`, an error is returned
+/// instead of blocking on a read that nobody will answer
+fn confirm_deletion(
+    assume_yes: bool,
+    interactive: bool,
+    expected: Option<&str>,
+) -> Result<bool, Error> {
+    if assume_yes {
+        return Ok(true);
+    }
 
-    let matches = App::new(crate_name!())
-        .version(crate_version!())
-        .author(crate_authors!())
-        .about("Prunes scaleway container registries")
-        .arg(
-            Arg::with_name("keep-last")
-                .help("Keep the last n versions")
-                .long("keep-last")
-                .validator(validate_parsable::<u64>)
-                .value_name("n"),
-        )
-        .arg(
-            Arg::with_name("region")
-                .env("SCW_REGION")
-                .help("The target region")
-                .required(true)
-                .long("region"),
-        )
-        .arg(
-            Arg::with_name("token")
-                .env("SCW_TOKEN")
-                .help("Authentication token")
-                .long("scw-token")
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("IMAGE")
-                .index(1)
-                .required(true)
-                .validator(validate_image_arg)
-                .value_name("NAMESPACE/IMAGE"),
-        )
-        .get_matches();
+    if !interactive {
+        return Err(Error::NonInteractiveConfirmation);
+    }
+
+    match expected {
+        Some(expected) => eprint!("Type `{}' to continue: ", expected),
+        None => eprint!("Do you want to continue? [y/N] "),
+    }
+
+    io::stderr().flush().unwrap();
 
-    let options = parse_args(matches);
-    let registry = Registry::new(options.token.clone(), options.region.clone());
+    match read_answer_from_stdin() {
+        Ok(answer) => Ok(is_confirmed(&answer, expected)),
+        Err(_) => Ok(false),
+    }
+}
 
-    // Find the image by its provided name, then verify that it's in the correct namespace,
-    // otherwise return an error
-    let (_, image) = get_namespace_and_image(&registry, &options.namespace, &options.image).await?;
+/// Returns true if `answer` confirms the prompt, which is an exact match of `expected` if given
+/// and `y` otherwise
+fn is_confirmed(answer: &str, expected: Option<&str>) -> bool {
+    match expected {
+        Some(expected) => answer == expected,
+        None => answer == "y" || answer == "Y",
+    }
+}
 
-    // Get all tags for the image
-    let mut tags = registry.image_tags(image.id()).await?;
+/// Returns what must be typed to confirm the deletion of the given `plans`: the
+/// `namespace/image` name of a single image, or the name of the namespace if every image is in
+/// the same one
+///
+/// When the images span several namespaces, the number of tags to delete is asked for instead.
+fn confirmation_phrase(plans: &[ImagePlan], namespaces: &[Namespace]) -> String {
+    let namespace_names = plans
+        .iter()
+        .map(|p| {
+            namespaces
+                .iter()
+                .find(|ns| ns.id() == p.image.namespace_id())
+                .map(|ns| ns.name())
+        })
+        .collect::<Vec<_>>();
 
-    if tags.is_empty() {
-        return Err(Error::NoImageTagsError);
+    match (plans, namespace_names[0]) {
+        ([p], Some(ns)) => format!("{}/{}", ns, p.image.name()),
+        (_, Some(ns)) if namespace_names.iter().all(|&name| name == Some(ns)) => ns.to_string(),
+        _ => format!(
+            "delete {} tags",
+            plans.iter().map(|p| p.plan.tags().len()).sum::<usize>()
+        ),
     }
+}
+
+/// Sorts `tags` by the given `field`, in the opposite order if `reverse` is true
+fn sort_tags<T: Borrow<ImageTag>>(tags: &mut [T], field: SortField, reverse: bool) {
+    tags.sort_by(|a, b| {
+        let (a, b) = (a.borrow(), b.borrow());
+        let ordering = match field {
+            SortField::Updated => b.updated_at().cmp(&a.updated_at()),
+            SortField::Created => b.created_at().cmp(&a.created_at()),
+            SortField::Name => a.name().cmp(b.name()),
+        };
+
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
 
-    tags.sort_by(|a, b| a.updated_at().cmp(&b.updated_at()));
-    tags.reverse();
+/// Returns `format_age` of `age`, highlighted if the tag is older than `OLD_TAG_AGE_DAYS`
+fn style_age(age: Duration) -> StyledObject<String> {
+    let styled = style(format_age(age));
 
-    let filtered_tags = filter_image_tags(&options, &tags);
+    if age > Duration::days(OLD_TAG_AGE_DAYS) {
+        styled.red()
+    } else {
+        styled
+    }
+}
 
-    if filtered_tags.is_empty() {
-        return Err(Error::NoMatchingImageTagsError);
+/// Returns a human readable description of `age` in whole days, e.g. `42 days old`
+///
+/// Negative ages caused by clock skew are shown as `0 days old`.
+fn format_age(age: Duration) -> String {
+    match age.num_days().max(0) {
+        1 => "1 day old".to_owned(),
+        days => format!("{} days old", days),
     }
+}
+
+/// Returns an aligned table of the tags that are about to be deleted from each image in `plans`,
+/// with their age relative to `now`
+fn format_deletion_table(plans: &[(&Image, Vec<&ImageTag>)], now: DateTime<Utc>) -> String {
+    let rows = plans
+        .iter()
+        .flat_map(|(image, tags)| {
+            tags.iter().map(move |t| {
+                [
+                    format!("{}:{}", image.name(), t.name()),
+                    table::short_digest(t).to_owned(),
+                    table::format_date_time(t.updated_at()),
+                    format_age(t.age_at(now)),
+                ]
+            })
+        })
+        .collect::<Vec<_>>();
+
+    table::format_table(["TAG", "DIGEST", "UPDATED", "AGE"], &rows)
+}
 
-    println!("This will delete the following images:");
+/// Returns a line describing `namespace`, to confirm the right namespace is being pruned
+fn format_namespace_header(namespace: &Namespace) -> String {
+    let visibility = if namespace.is_public() {
+        "public"
+    } else {
+        "private"
+    };
+    let header = format!(
+        "Namespace {} ({}, {} images)",
+        namespace.name(),
+        visibility,
+        namespace.image_count()
+    );
 
-    for t in filtered_tags.iter() {
-        println!("{}:{}\t{}", image.name(), t.name(), t.updated_at());
+    match namespace.description() {
+        "" => header,
+        description => format!("{}: {}", header, description),
     }
+}
 
-    print!("Do you want to continue? [y/N] ");
-    io::stdout().flush().unwrap();
+/// Prints the list of tags that are about to be deleted from each image in `plans` in the given
+/// `format`
+///
+/// The human-readable formats start with a line of context about each of the `namespaces` the
+/// images belong to.
+fn print_deletion_plan(
+    plans: &[(&Image, Vec<&ImageTag>)],
+    namespaces: &[&Namespace],
+    format: OutputFormat,
+) {
+    if !format.is_machine_readable() {
+        for namespace in namespaces.iter() {
+            println!("{}", format_namespace_header(namespace));
+        }
+    }
 
-    if let Ok(answer) = read_answer_from_stdin() {
-        if answer == "y" || answer == "Y" {
-            let ps = ProgressStyle::default_bar().template("{prefix} {wide_bar} {pos}/{len}");
-            let pb = ProgressBar::new(filtered_tags.len() as u64).with_style(ps);
+    match format {
+        OutputFormat::Text => {
+            println!("This will delete the following images:");
 
-            for tag in filtered_tags.iter() {
-                pb.set_prefix(&format!("{}:{}", image.name(), tag.name()));
-                registry.delete_image_by_tag(tag.id(), false).await?;
-                pb.inc(1);
+            for (image, tags) in plans.iter() {
+                for t in tags.iter() {
+                    println!(
+                        "{}:{}\t{} ({})",
+                        image.name(),
+                        style(t.name()).cyan(),
+                        t.updated_at(),
+                        style_age(t.age())
+                    );
+                }
             }
+        }
+        OutputFormat::Table => {
+            println!("This will delete the following images:");
+            print!("{}", format_deletion_table(plans, Utc::now()));
+        }
+        OutputFormat::Json => {
+            let plan: Vec<PlannedDeletion> = plans
+                .iter()
+                .flat_map(|(image, tags)| tags.iter().map(move |t| PlannedDeletion::new(image, t)))
+                .collect();
+            let mut stdout = io::stdout();
 
-            pb.finish();
+            serde_json::to_writer(&mut stdout, &plan).expect("unable to write deletion plan");
+            writeln!(stdout).unwrap();
         }
+        OutputFormat::Csv => print!("{}", format_deletion_csv(plans)),
     }
-
-    Ok(())
 }
 
-fn main() {
-    use tokio::runtime::Runtime;
-    let mut rt = Runtime::new().expect("unable to create async runtime");
+/// Returns the deletion plan as CSV with a header row and a row for each tag
+fn format_deletion_csv(plans: &[(&Image, Vec<&ImageTag>)]) -> String {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let timestamp = |t: DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::AutoSi, true);
 
-    match rt.block_on(try_main()) {
-        Ok(_) => {}
-        Err(e) => {
-            println!("There was an error: {}", e);
+    writer
+        .write_record(["image", "tag", "digest", "created_at", "updated_at", "id"])
+        .unwrap();
+
+    for (image, tags) in plans.iter() {
+        for t in tags.iter() {
+            writer
+                .write_record([
+                    image.name(),
+                    t.name(),
+                    t.digest(),
+                    &timestamp(t.created_at()),
+                    &timestamp(t.updated_at()),
+                    t.id(),
+                ])
+                .unwrap();
         }
     }
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+/// Prints the number of tags about to be deleted from each image in `plans`
+///
+/// This is only useful when more than a single image is pruned, as the count is otherwise obvious
+/// from the deletion plan.
+fn print_deletion_summary(plans: &[ImagePlan]) {
+    eprintln!("Tags to delete per image:");
+
+    for plan in plans.iter() {
+        eprintln!("  {}: {}", plan.image.name(), plan.plan.tags().len());
+    }
+}
+
+/// Returns the metrics of the run described by `report`, where `reclaimed` is the estimated storage
+/// freed by the deletions
+fn report_metrics(report: &PruneReport, reclaimed: Option<u64>) -> Metrics {
+    Metrics {
+        deleted: report.deleted.len(),
+        failed: report.failed.len(),
+        skipped: report.skipped.len() + report.changed.len(),
+        reclaimed_bytes: reclaimed.unwrap_or(0),
+        duration: report.elapsed,
+        finished_at: Utc::now(),
+    }
+}
+
+/// Returns the summary printed by `--count-only` for a plan deleting `deleted` of `total` tags
+fn count_summary(deleted: usize, total: usize) -> String {
+    format!(
+        "Would delete {} of {} tags (keeping {})",
+        deleted,
+        total,
+        total - deleted
+    )
+}
+
+/// Returns the storage freed by deleting `deleted` of the `total` tags of an image of `image_size`
+/// bytes
+///
+/// The API doesn't report the size of individual tags, so the image size is assumed to be spread
+/// evenly across its tags. Returns `None` when the image size is unknown.
+fn estimate_reclaimed_size(image_size: usize, total: usize, deleted: usize) -> Option<u64> {
+    if image_size == 0 || total == 0 {
+        return None;
+    }
+
+    Some((image_size as u64).saturating_mul(deleted as u64) / total as u64)
+}
+
+/// Returns the summary of a prune that deleted `deleted` tags, freeing `reclaimed` bytes
+fn reclaimed_summary(deleted: usize, reclaimed: Option<u64>) -> String {
+    match reclaimed {
+        Some(bytes) => format!(
+            "Reclaimed approximately {:.1} MiB across {} tags",
+            bytes as f64 / (1024.0 * 1024.0),
+            deleted
+        ),
+        None => format!("Deleted {} tags", deleted),
+    }
+}
+
+/// Prints the tags that `report` failed to delete, along with the error
+fn print_failed_deletions(report: &PruneReport) {
+    for ((image, tag), err) in report.failed.iter() {
+        eprintln!("Failed to delete {}:{}: {}", image.name(), tag.name(), err);
+    }
+}
+
+/// Returns the `deleted` tags that are still ready in the `current` tags of their image
+fn remaining_tags<'a>(deleted: &[&'a ImageTag], current: &[ImageTag]) -> Vec<&'a ImageTag> {
+    let ready = current
+        .iter()
+        .filter(|tag| tag.status() == Status::Ready)
+        .map(|tag| tag.id())
+        .collect::<HashSet<&str>>();
+
+    deleted
+        .iter()
+        .copied()
+        .filter(|tag| ready.contains(tag.id()))
+        .collect()
+}
+
+/// Returns the first of the given tag `names` that isn't among `tags`, if any
+fn missing_tag<'a>(tags: &[ImageTag], names: &'a [String]) -> Option<&'a str> {
+    names
+        .iter()
+        .find(|name| tags.iter().all(|t| t.name() != name.as_str()))
+        .map(String::as_str)
+}
+
+/// Returns the `tags` whose name is one of the given `names`
+fn named_tags<'a>(tags: &'a [ImageTag], names: &[String]) -> Vec<&'a ImageTag> {
+    tags.iter()
+        .filter(|t| names.iter().any(|name| name == t.name()))
+        .collect()
+}
+
+/// Returns whether each of the `tags` is one of the `selected` tags, to check them up front in the
+/// interactive selection
+fn preselected_tags(tags: &[ImageTag], selected: &[&ImageTag]) -> Vec<bool> {
+    let ids = selected.iter().map(|t| t.id()).collect::<HashSet<&str>>();
+
+    tags.iter().map(|t| ids.contains(t.id())).collect()
+}
+
+/// Returns the `tags` at the given `indices` of the interactive selection
+fn tags_at<'a>(tags: &'a [ImageTag], indices: &[usize]) -> Vec<&'a ImageTag> {
+    indices.iter().map(|&i| &tags[i]).collect()
+}
+
+/// Lets the user pick which of the `tags` of `image` to delete, with the `selected` tags checked
+/// up front, and returns the picked tags
+fn select_tags<'a>(
+    image: &Image,
+    tags: &'a [ImageTag],
+    selected: &[&ImageTag],
+) -> Result<Vec<&'a ImageTag>, Error> {
+    let items = tags
+        .iter()
+        .map(|t| format!("{}\t{} ({})", t.name(), t.updated_at(), format_age(t.age())))
+        .collect::<Vec<_>>();
+    let indices = MultiSelect::new()
+        .with_prompt(format!("Tags of {} to delete", image.name()))
+        .items(&items)
+        .defaults(&preselected_tags(tags, selected))
+        .interact()
+        .map_err(Error::SelectionError)?;
+
+    Ok(tags_at(tags, &indices))
+}
+
+/// Returns true if any of the `tags` of an image isn't on its way out
+fn has_remaining_tags(tags: &[ImageTag]) -> bool {
+    tags.iter()
+        .any(|tag| !matches!(tag.status(), Status::Deleting | Status::Deleted))
+}
+
+/// Returns a spinner showing `message` while waiting for the API, or a hidden one unless `show` is
+/// true
+fn new_spinner(show: bool, message: &str) -> ProgressBar {
+    if !show {
+        return ProgressBar::hidden();
+    }
+
+    let spinner = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::default_spinner().template(SPINNER_TEMPLATE));
+
+    spinner.set_message(message);
+    spinner.enable_steady_tick(100);
+    spinner
+}
+
+/// Calls `delete` for each of the given `deletions` with at most `concurrency` deletions in
+/// flight at once, incrementing the progress bar `pb` as each deletion completes
+///
+/// A failed deletion doesn't stop the remaining ones - instead the deletions that failed are
+/// returned along with their error once every deletion has completed. Once `interrupt` is set, no
+/// new deletions are started and the ones that never started are returned as well.
+async fn delete_image_tags<D, F, Fut, T, E>(
+    deletions: impl IntoIterator<Item = D>,
+    concurrency: usize,
+    pb: &ProgressBar,
+    interrupt: &Interrupt,
+    delete: F,
+) -> (Vec<(D, E)>, Vec<D>)
+where
+    D: Copy,
+    F: Fn(D) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut deletions = deletions.into_iter();
+    let started = std::iter::from_fn(|| {
+        if interrupt.is_set() {
+            None
+        } else {
+            deletions.next()
+        }
+    });
+
+    let failed = stream::iter(started)
+        .map(|deletion| {
+            let fut = delete(deletion);
+
+            async move { (deletion, fut.await) }
+        })
+        .buffer_unordered(concurrency)
+        .filter_map(|(deletion, res)| async move {
+            pb.inc(1);
+
+            res.err().map(|err| (deletion, err))
+        })
+        .collect()
+        .await;
+
+    (failed, deletions.collect())
+}
+
+/// Polls each of the deleted `tags` until the API no longer has it or `timeout` elapses,
+/// incrementing the progress bar `pb` as each tag is gone, and returns the tags that remain
+///
+/// The API may respond to a deletion with the tag in `deleting` status and only remove it later,
+/// so a deleted tag isn't necessarily gone yet.
+async fn wait_for_deletions<'a>(
+    registry: &Registry,
+    tags: &[TagRef<'a>],
+    timeout: std::time::Duration,
+    pb: &ProgressBar,
+) -> Vec<TagRef<'a>> {
+    let deadline = Instant::now() + timeout;
+    let mut pending = tags.to_vec();
+
+    loop {
+        let mut remaining = Vec::new();
+
+        for (image, tag) in pending {
+            match registry.image_tag(tag.id()).await {
+                Err(ScalewaySdkError::NoSuchTag) => pb.inc(1),
+                Ok(current) if matches!(current.status(), Status::Deleted) => pb.inc(1),
+                Ok(_) => remaining.push((image, tag)),
+                Err(err) => {
+                    debug!("Unable to check {}:{}: {}", image.name(), tag.name(), err);
+                    remaining.push((image, tag));
+                }
+            }
+        }
+
+        pending = remaining;
+
+        if pending.is_empty() || Instant::now() + WAIT_POLL_INTERVAL > deadline {
+            return pending;
+        }
+
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Deletes the tags of the given `plans` with at most `concurrency` deletions in flight at once,
+/// incrementing the progress bar `pb` as each deletion completes, and returns the outcome
+///
+/// Each delete request waits for `rate_limiter` before it is sent, and the IDs of the deleted tags
+/// are appended to `resume_log` as soon as each deletion succeeds. Tags are only deleted if they
+/// still have the digest they were planned with, and the ones that were pushed again in the
+/// meantime are reported as changed rather than failed. Once `interrupt` is set, the deletions in
+/// flight are finished but no new ones are started.
+async fn prune<'a>(
+    registry: &Registry,
+    plans: &[ImagePlan<'a>],
+    concurrency: usize,
+    pb: &ProgressBar,
+    rate_limiter: Option<&RateLimiter>,
+    resume_log: Option<&ResumeLog>,
+    interrupt: &Interrupt,
+) -> PruneReport<'a> {
+    let deletions = plans
+        .iter()
+        .flat_map(|p| p.plan.deletions.iter().map(move |d| (p.image, d)))
+        .collect::<Vec<(&Image, &Deletion)>>();
+    let start = Instant::now();
+
+    let (failed, interrupted) = delete_image_tags(
+        deletions.iter().copied(),
+        concurrency,
+        pb,
+        interrupt,
+        |(_, deletion)| async move {
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let res = registry
+                .delete_image_by_tag_if_match(
+                    deletion.tag.id(),
+                    deletion.tag.digest(),
+                    deletion.force(),
+                )
+                .await;
+
+            if let (Ok(_), Some(resume_log)) = (&res, resume_log) {
+                let tags = std::iter::once(deletion.tag).chain(deletion.shared.iter().copied());
+
+                for tag in tags {
+                    if let Err(err) = resume_log.record(tag.id()) {
+                        log::warn!("Unable to record {} in the resume log: {}", tag.id(), err);
+                    }
+                }
+            }
+
+            res
+        },
+    )
+    .await;
+
+    let plans = plans.iter().map(|p| (p.image, &p.plan)).collect::<Vec<_>>();
+
+    let (changed, failed) = failed
+        .into_iter()
+        .partition::<Vec<_>, _>(|(_, err)| matches!(err, ScalewaySdkError::TagChanged { .. }));
+    let changed = changed
+        .into_iter()
+        .map(|(deletion, _)| deletion)
+        .collect::<Vec<_>>();
+
+    PruneReport::new(&plans, &failed, &changed, &interrupted, start.elapsed())
+}
+
+/// Returns the `clap` application with all the supported arguments
+fn build_app() -> App<'static, 'static> {
+    App::new(crate_name!())
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about("Prunes scaleway container registries")
+        .arg(
+            Arg::with_name("keep-last")
+                .help("Keep the last n versions")
+                .long("keep-last")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-last-digests")
+                .help("Keep every tag of the last n distinct digests")
+                .long("keep-last-digests")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-last-by-name")
+                .help("Keep the last n versions of each tag name")
+                .long("keep-last-by-name")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-daily")
+                .help("Keep the last tag of each of the last n days with a tag")
+                .long("keep-daily")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-weekly")
+                .help("Keep the last tag of each of the last n weeks with a tag")
+                .long("keep-weekly")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-monthly")
+                .help("Keep the last tag of each of the last n months with a tag")
+                .long("keep-monthly")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-last-semver")
+                .help("Keep the n highest semantic versions, and every tag that isn't one")
+                .long("keep-last-semver")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("prune-non-semver")
+                .help("Allow pruning tags that aren't semantic versions with --keep-last-semver")
+                .long("prune-non-semver")
+                .requires("keep-last-semver"),
+        )
+        .arg(
+            Arg::with_name("min-keep")
+                .help("Never prune the n most recent versions, regardless of any other option")
+                .long("min-keep")
+                .validator(validate_parsable::<u64>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("keep-within")
+                .help("Keep versions updated within the given duration, e.g. 3d")
+                .long("keep-within")
+                .validator(validate_duration)
+                .value_name("duration"),
+        )
+        .arg(
+            Arg::with_name("newer-than")
+                .help("Only prune versions updated within the given duration, e.g. 1h")
+                .long("newer-than")
+                .validator(validate_duration)
+                .value_name("duration"),
+        )
+        .arg(
+            Arg::with_name("older-than")
+                .help("Keep versions updated at or after the given date, e.g. 2020-04-01")
+                .long("older-than")
+                .validator(validate_date_time)
+                .value_name("date"),
+        )
+        .arg(
+            Arg::with_name("since-digest")
+                .help("Keep versions updated at or after the last tag with the given digest")
+                .long("since-digest")
+                .value_name("digest"),
+        )
+        .arg(
+            Arg::with_name("by")
+                .help("Whether --keep-within and --older-than look at when a version was updated or created")
+                .long("by")
+                .possible_values(&["updated", "created"])
+                .default_value("updated")
+                .value_name("field"),
+        )
+        .arg(
+            Arg::with_name("match")
+                .help("Only prune versions whose tag matches the given regular expression")
+                .long("match")
+                .validator(validate_regex)
+                .value_name("regex"),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .help("Never prune versions whose tag matches the given glob, can be repeated")
+                .long("exclude")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_glob)
+                .value_name("glob"),
+        )
+        .arg(
+            Arg::with_name("protect-latest")
+                .help("Never prune the `latest' tag, this is the default")
+                .long("protect-latest")
+                .overrides_with("no-protect-latest"),
+        )
+        .arg(
+            Arg::with_name("no-protect-latest")
+                .help("Allow the `latest' tag to be pruned")
+                .long("no-protect-latest")
+                .overrides_with("protect-latest"),
+        )
+        .arg(
+            Arg::with_name("region")
+                .env("SCW_REGION")
+                .help("The target region")
+                .long("region")
+                .validator(validate_region),
+        )
+        .arg(
+            Arg::with_name("endpoint")
+                .env("SCW_API_URL")
+                .help("The registry API endpoint to use instead of the one of the region")
+                .long("endpoint")
+                .validator(validate_endpoint)
+                .value_name("url"),
+        )
+        .arg(
+            Arg::with_name("proxy")
+                .help("The proxy to send API requests through")
+                .long("proxy")
+                .value_name("url"),
+        )
+        .arg(
+            Arg::with_name("token")
+                .env("SCW_TOKEN")
+                .help("Authentication token, takes precedence over --secret-key")
+                .long("scw-token"),
+        )
+        .arg(
+            Arg::with_name("secret-key")
+                .env("SCW_SECRET_KEY")
+                .help("Secret key of a Scaleway API key, used when no token is given")
+                .long("secret-key"),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .env("SCW_PROFILE")
+                .help("The profile in the Scaleway config file to read defaults from")
+                .long("profile"),
+        )
+        .arg(
+            Arg::with_name("IMAGE")
+                .help("The images to prune, or every image in the namespace if no image is given")
+                .index(1)
+                .required_unless_one(&["from-file", "apply-plan"])
+                .multiple(true)
+                .validator(validate_image_arg)
+                .value_name("NAMESPACE[/IMAGE]"),
+        )
+        .arg(
+            Arg::with_name("from-file")
+                .help("Read the images to prune from the given file, one per line, or - for stdin")
+                .long("from-file")
+                .value_name("path"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .help("Print the tags that would be deleted and exit without deleting them")
+                .long("dry-run"),
+        )
+        .arg(
+            Arg::with_name("count-only")
+                .help("Print the number of tags that would be deleted and kept and exit without deleting them")
+                .long("count-only")
+                .conflicts_with_all(&["list", "stats", "interactive", "plan-file"]),
+        )
+        .arg(
+            Arg::with_name("plan-file")
+                .help("Write the deletion plan to the given file for --apply-plan and exit without deleting anything")
+                .long("plan-file")
+                .value_name("path")
+                .conflicts_with_all(&["list", "stats", "apply-plan"]),
+        )
+        .arg(
+            Arg::with_name("diff-against")
+                .help("Show the tags that entered or left the deletion plan since a plan written with --plan-file")
+                .long("diff-against")
+                .value_name("path")
+                .conflicts_with_all(&["list", "stats"]),
+        )
+        .arg(
+            Arg::with_name("apply-plan")
+                .help("Delete exactly the tags of a plan written with --plan-file, after checking that they still exist")
+                .long("apply-plan")
+                .value_name("path")
+                .conflicts_with_all(&[
+                    "IMAGE",
+                    "from-file",
+                    "tag",
+                    "interactive",
+                    "list",
+                    "stats",
+                    "keep-last",
+                    "keep-last-digests",
+                    "keep-last-by-name",
+                    "keep-daily",
+                    "keep-weekly",
+                    "keep-monthly",
+                    "keep-last-semver",
+                    "keep-within",
+                    "newer-than",
+                    "older-than",
+                    "since-digest",
+                    "min-keep",
+                    "match",
+                    "exclude",
+                    "by",
+                    "no-protect-latest",
+                    "prune-non-semver",
+                ]),
+        )
+        .arg(
+            Arg::with_name("list")
+                .help("Print every tag of the images and exit without deleting anything")
+                .long("list"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .help("Print the number of tags, digests and the storage of the images and exit")
+                .long("stats")
+                .conflicts_with("list"),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .help("Delete the tag with the given name, ignoring the retention rules, can be repeated")
+                .long("tag")
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("name"),
+        )
+        .arg(
+            Arg::with_name("interactive")
+                .help("Pick the tags to delete from a list, with the tags matching the rules checked")
+                .long("interactive")
+                .short("i")
+                .conflicts_with("quiet"),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .help("Delete the tags without asking for confirmation")
+                .short("y")
+                .long("yes"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .help("Only print errors, requires --yes as there's no prompt")
+                .short("q")
+                .long("quiet")
+                .requires("yes"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("The format of the deletion plan")
+                .long("output")
+                .possible_values(&["text", "json", "table", "csv"])
+                .default_value("text")
+                .value_name("format"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .help("When to color the output, auto colors it when writing to a terminal unless NO_COLOR is set")
+                .long("color")
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto")
+                .value_name("when"),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .help("The order of the tags in the deletion plan")
+                .long("sort")
+                .possible_values(&["updated", "created", "name"])
+                .default_value("updated")
+                .value_name("field"),
+        )
+        .arg(
+            Arg::with_name("visibility")
+                .help("Only prune the images of a namespace with the given visibility")
+                .long("visibility")
+                .possible_values(&["public", "private"])
+                .value_name("visibility"),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .help("Reverse the order of the tags in the deletion plan")
+                .long("reverse"),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .help("The maximum number of tags to delete at the same time")
+                .long("concurrency")
+                .validator(validate_parsable::<NonZeroUsize>)
+                .default_value("4")
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("image-concurrency")
+                .help("The maximum number of images to list the tags of at once")
+                .long("image-concurrency")
+                .validator(validate_parsable::<NonZeroUsize>)
+                .default_value("4")
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("confirm-threshold")
+                .help(
+                    "Require typing the name of the image to confirm deleting more than this \
+                     many tags",
+                )
+                .long("confirm-threshold")
+                .validator(validate_parsable::<usize>)
+                .default_value("50")
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("max-rps")
+                .help("The maximum number of delete requests to send per second")
+                .long("max-rps")
+                .validator(validate_parsable::<NonZeroU32>)
+                .value_name("n"),
+        )
+        .arg(
+            Arg::with_name("resume-log")
+                .help("Log the deleted tags to the given file and skip the ones logged by earlier runs")
+                .long("resume-log")
+                .value_name("path"),
+        )
+        .arg(
+            Arg::with_name("cache")
+                .help("Cache the namespace and image listings in the given file between runs")
+                .long("cache")
+                .value_name("path"),
+        )
+        .arg(
+            Arg::with_name("cache-ttl")
+                .help("The maximum age of the cached listings")
+                .long("cache-ttl")
+                .validator(validate_duration)
+                .default_value("5m")
+                .value_name("duration"),
+        )
+        .arg(
+            Arg::with_name("force-shared-digests")
+                .help("Delete tags that share their digest only with other pruned tags")
+                .long("force-shared-digests"),
+        )
+        .arg(
+            Arg::with_name("protect-tags-with-digest-in-use")
+                .help("Skip tags whose digest is referenced by another image of the namespace")
+                .long("protect-tags-with-digest-in-use"),
+        )
+        .arg(
+            Arg::with_name("delete-empty-images")
+                .help("Delete the images that have no tags left after pruning")
+                .long("delete-empty-images"),
+        )
+        .arg(
+            Arg::with_name("metrics-file")
+                .help("Write metrics about the run to the given file for node_exporter's textfile collector")
+                .long("metrics-file")
+                .value_name("path"),
+        )
+        .arg(
+            Arg::with_name("pre-delete-hook")
+                .help("Run the given shell command with the deletion plan as JSON on stdin before deleting, and abort if it fails")
+                .long("pre-delete-hook")
+                .value_name("cmd"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .help("List the tags again after deleting them and warn about any that remain")
+                .long("verify"),
+        )
+        .arg(
+            Arg::with_name("wait")
+                .help("Wait for the API to finish deleting the tags before reporting them as deleted")
+                .long("wait"),
+        )
+        .arg(
+            Arg::with_name("wait-timeout")
+                .help("The maximum time to wait for the deletions with --wait, e.g. 90s or 10m")
+                .long("wait-timeout")
+                .value_name("duration")
+                .default_value("5m")
+                .validator(|s| parse_duration(&s).map(|_| ())),
+        )
+}
+
+async fn try_main() -> Result<(), Error> {
+    env_logger::init();
+
+    let matches = build_app().get_matches();
+
+    let profile = if needs_config(&matches) {
+        let config = match Config::default_path() {
+            Some(path) => Config::load(&path)?,
+            None => Config::default(),
+        };
+
+        config.profile(matches.value_of("profile"))?
+    } else {
+        Profile::default()
+    };
+    let options = parse_args(matches, &profile)?;
+
+    let no_color = env::var_os("NO_COLOR").is_some();
+
+    console::set_colors_enabled(
+        options
+            .color
+            .is_enabled(io::stdout().is_terminal(), no_color),
+    );
+    console::set_colors_enabled_stderr(
+        options
+            .color
+            .is_enabled(io::stderr().is_terminal(), no_color),
+    );
+
+    let mut metrics = Metrics::default();
+    let result = run(&options, &mut metrics).await;
+
+    // The metrics are written whatever the outcome, so that a run that had nothing to delete or
+    // failed doesn't leave the metrics of the previous run behind
+    let written = match options.metrics_file.as_deref() {
+        Some(path) => {
+            metrics.finished_at = Utc::now();
+            metrics.write(path).map_err(Error::MetricsWriteError)
+        }
+        None => Ok(()),
+    };
+
+    result.and(written)
+}
+
+/// Prunes the targets of `options`, filling in the `metrics` of the deletions if any are made
+async fn run(options: &Options, metrics: &mut Metrics) -> Result<(), Error> {
+    if options.interactive && !(io::stdin().is_terminal() && io::stderr().is_terminal()) {
+        return Err(Error::NonInteractiveSelection);
+    }
+
+    if let Some(reason) = region_limitation(&options.region) {
+        log::warn!(
+            "The registry may not be available in {}: {}",
+            options.region,
+            reason
+        );
+    }
+
+    if let Some(plan_file) = options.apply_plan.as_ref() {
+        if plan_file.region != options.region {
+            return Err(Error::PlanRegionMismatch(plan_file.region.clone()));
+        }
+    }
+
+    // A tag that is already gone, e.g. because a previous run deleted it, isn't a failure
+    let mut registry =
+        Registry::new(options.token.clone(), options.region.clone()).idempotent_deletes(true);
+
+    if let Some(ref endpoint) = options.endpoint {
+        registry = registry.endpoint(endpoint);
+    }
+
+    if let Some(ref proxy) = options.proxy {
+        registry = registry.proxy(proxy).map_err(Error::InvalidProxy)?;
+    }
+
+    // Each target is pruned independently, so a failing target is reported while the others are
+    // still pruned - unless it's the only one, in which case its error is returned as is
+    let single_target = options.targets.len() == 1;
+    let mut failed_targets = 0;
+    let mut report_failure = |target: &dyn Display, err: Error| {
+        if single_target {
+            return Err(err);
+        }
+
+        eprintln!("Unable to prune {}: {}", target, err);
+        failed_targets += 1;
+
+        Ok(())
+    };
+
+    // indicatif doesn't draw anything when stderr isn't a terminal, so progress only needs to be
+    // hidden explicitly to keep the JSON and CSV output clean and to keep quiet
+    let show_progress = !options.quiet && !options.output.is_machine_readable();
+
+    // List the namespaces and images once, then find the images of each target in them
+    let spinner = new_spinner(show_progress, "Listing namespaces and images");
+    // A listing is only reused by runs against the same API and account
+    let cache_key = Cache::key(&options.region, options.endpoint.as_deref(), &options.token);
+    let listing = list_namespaces_and_images(
+        &registry,
+        options.cache.as_deref(),
+        &cache_key,
+        options.cache_ttl,
+    )
+    .await;
+
+    spinner.finish_and_clear();
+
+    let Listing {
+        namespaces,
+        images: all_images,
+        ..
+    } = listing?;
+    let mut images: Vec<(&Image, bool)> = Vec::new();
+
+    for target in options.targets.iter() {
+        match find_namespace_and_images(
+            &namespaces,
+            &all_images,
+            &target.namespace,
+            target.image.as_deref(),
+        ) {
+            Ok((namespace, found)) => {
+                // Explicitly given images are pruned regardless of their visibility
+                let found = found.into_iter().filter(|image| {
+                    target.image.is_some()
+                        || options
+                            .visibility
+                            .is_none_or(|v| image_visibility(image, namespace) == v)
+                });
+
+                for image in found {
+                    if images.iter().all(|(i, _)| i.id() != image.id()) {
+                        images.push((image, target.image.is_some()));
+                    }
+                }
+            }
+            Err(err) => report_failure(target, err)?,
+        }
+    }
+
+    // The images of a plan are looked up by ID, since the plan refers to them and not to targets
+    for planned in options.apply_plan.iter().flat_map(|p| p.images.iter()) {
+        match all_images.iter().find(|image| image.id() == planned.id) {
+            Some(image) => images.push((image, true)),
+            None => return Err(Error::StalePlan(planned.name.clone())),
+        }
+    }
+
+    // Get all tags for each image, listing the tags of several images at once
+    let spinner = new_spinner(
+        show_progress && images.len() > 1,
+        &format!("Listing the tags of {} images", images.len()),
+    );
+    let listed_tags = stream::iter(images.iter().copied())
+        .map(|(image, is_explicit)| {
+            let registry = &registry;
+
+            async move {
+                (
+                    image,
+                    is_explicit,
+                    registry.image_tags_all(image.id()).await,
+                )
+            }
+        })
+        .buffered(options.image_concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    spinner.finish_and_clear();
+
+    let mut image_tags = Vec::with_capacity(images.len());
+
+    for (image, is_explicit, tags) in listed_tags {
+        let mut tags = match tags {
+            Ok(tags) => tags,
+            Err(err) => {
+                report_failure(&image.name(), err.into())?;
+                continue;
+            }
+        };
+
+        if tags.is_empty() && is_explicit {
+            report_failure(&image.name(), Error::NoImageTagsError)?;
+            continue;
+        }
+
+        // Images of a namespace-wide target without the digest keep every tag instead
+        if let (Some(digest), true) = (options.filter.since_digest.as_deref(), is_explicit) {
+            if digest_cutoff(&tags, digest).is_none() {
+                let err = Error::NoSuchDigest(image.name().to_owned(), digest.to_owned());
+
+                report_failure(&image.name(), err)?;
+                continue;
+            }
+        }
+
+        // Images of a namespace-wide target only need to have some of the named tags
+        if let (Some(name), true) = (missing_tag(&tags, &options.tags), is_explicit) {
+            let tag = format!("{}:{}", image.name(), name);
+
+            report_failure(&image.name(), Error::NoSuchTag(tag))?;
+            continue;
+        }
+
+        // The retention rules always work on the most recently updated tags first, regardless of
+        // how the plan is displayed
+        sort_tags(&mut tags, SortField::Updated, false);
+        image_tags.push((image, tags));
+    }
+
+    let now = Utc::now();
+
+    if options.list {
+        let rows = image_tags
+            .iter()
+            .flat_map(|(image, tags)| tags.iter().map(move |tag| (*image, tag)))
+            .collect::<Vec<_>>();
+
+        print!("{}", format_tag_table(&rows, now));
+
+        return match failed_targets {
+            0 => Ok(()),
+            n => Err(Error::FailedTargets(n)),
+        };
+    }
+
+    if options.stats {
+        print!("{}", Stats::new(&image_tags).format(now));
+
+        return match failed_targets {
+            0 => Ok(()),
+            n => Err(Error::FailedTargets(n)),
+        };
+    }
+
+    if let (Some(window), false) = (options.filter.newer_than, options.quiet) {
+        eprintln!(
+            "Warning: --newer-than selects the tags updated within the last {} for deletion, \
+             rather than the old ones",
+            humantime::format_duration(window.to_std().unwrap())
+        );
+    }
+
+    let rate_limiter = options.max_rps.map(RateLimiter::per_second);
+    let resume_log = options
+        .resume_log
+        .as_deref()
+        .map(ResumeLog::open)
+        .transpose()
+        .map_err(Error::ResumeLogError)?;
+    // The tags of the other images of the namespaces are only needed to tell whether they
+    // reference the digests about to be deleted
+    let other_image_tags = if options.protect_digests_in_use {
+        let namespace_ids = image_tags
+            .iter()
+            .map(|(image, _)| image.namespace_id())
+            .collect::<HashSet<_>>();
+        let others = all_images.iter().filter(|image| {
+            namespace_ids.contains(image.namespace_id())
+                && image_tags.iter().all(|(i, _)| i.id() != image.id())
+        });
+        let spinner = new_spinner(show_progress, "Listing the tags of the other images");
+        let listed = stream::iter(others)
+            .map(|image| {
+                let registry = &registry;
+
+                async move { (image, registry.image_tags_all(image.id()).await) }
+            })
+            .buffered(options.image_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        spinner.finish_and_clear();
+
+        listed
+            .into_iter()
+            .map(|(image, tags)| tags.map(|tags| (image, tags)))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        Vec::new()
+    };
+    let digest_users = index_digests(
+        image_tags
+            .iter()
+            .chain(other_image_tags.iter())
+            .map(|(image, tags)| (*image, &tags[..])),
+    );
+    let mut plans = Vec::new();
+
+    for (image, tags) in image_tags.iter() {
+        let mut filtered_tags = if let Some(plan_file) = options.apply_plan.as_ref() {
+            let planned = plan_file
+                .images
+                .iter()
+                .find(|p| p.id == image.id())
+                .unwrap();
+
+            planned
+                .resolve(tags)
+                .map_err(|t| Error::StalePlan(format!("{}:{}", image.name(), t.name)))?
+        } else if options.tags.is_empty() {
+            filter_image_tags(&options.filter, tags, now)
+        } else {
+            named_tags(tags, &options.tags)
+        };
+
+        if options.interactive {
+            filtered_tags = select_tags(image, tags, &filtered_tags)?;
+        }
+
+        // A saved plan only holds tags sharing a digest if they were allowed to be deleted
+        // together, and tags pushed since with the same digest still cause them to be skipped
+        let force_shared_digests = options.force_shared_digests || options.apply_plan.is_some();
+        let mut plan = plan_deletions(tags, &filtered_tags, force_shared_digests);
+
+        if let Some(resume_log) = resume_log.as_ref() {
+            plan.skip_deleted(|tag| resume_log.contains(tag.id()));
+        }
+
+        if options.protect_digests_in_use {
+            plan.skip_digests_in_use(|digest| {
+                digest_users
+                    .get(digest)?
+                    .iter()
+                    .find(|i| i.id() != image.id())
+                    .map(|i| i.name().to_owned())
+            });
+        }
+
+        let skipped = if options.quiet {
+            &[][..]
+        } else {
+            &plan.skipped[..]
+        };
+
+        for (tag, reason) in skipped.iter() {
+            let reason = match reason {
+                SkipReason::SharedWithKeptTag => {
+                    "its digest is shared with a tag that is kept".to_owned()
+                }
+                SkipReason::SharedDigest => {
+                    "its digest is shared with other tags, use --force-shared-digests to delete them"
+                        .to_owned()
+                }
+                SkipReason::NotReady(status) => format!("its status is `{}'", status),
+                SkipReason::AlreadyDeleted => "it was deleted by a previous run".to_owned(),
+                SkipReason::DigestInUse(other) => {
+                    format!("its digest is in use by the image {}", other)
+                }
+            };
+
+            let line = format!("Skipping {}:{}: {}", image.name(), tag.name(), reason);
+
+            eprintln!("{}", style(line).dim());
+        }
+
+        if !plan.is_empty() {
+            plans.push(ImagePlan { image, tags, plan });
+        }
+    }
+
+    if options.count_only {
+        let deleted: usize = plans.iter().map(|p| p.plan.tags().len()).sum();
+        let total: usize = image_tags.iter().map(|(_, tags)| tags.len()).sum();
+
+        println!("{}", count_summary(deleted, total));
+
+        return match failed_targets {
+            0 => Ok(()),
+            n => Err(Error::FailedTargets(n)),
+        };
+    }
+
+    if plans.is_empty() {
+        if failed_targets > 0 {
+            return Err(Error::FailedTargets(failed_targets));
+        }
+
+        return Err(Error::NoMatchingImageTagsError);
+    }
+
+    let plan_tags = plans
+        .iter()
+        .map(|p| {
+            let mut tags = p.plan.tags();
+
+            sort_tags(&mut tags, options.sort, options.reverse);
+            (p.image, tags)
+        })
+        .collect::<Vec<_>>();
+
+    if !options.quiet {
+        let plan_namespaces = namespaces
+            .iter()
+            .filter(|ns| plans.iter().any(|p| p.image.namespace_id() == ns.id()))
+            .collect::<Vec<_>>();
+
+        print_deletion_plan(&plan_tags, &plan_namespaces, options.output);
+    }
+
+    if let Some(previous) = options.diff_against.as_ref() {
+        let current = PlanFile::new(&options.region, &plan_tags, now);
+
+        eprint!("{}", current.diff(previous).format());
+    }
+
+    if let Some(path) = options.plan_file.as_deref() {
+        PlanFile::new(&options.region, &plan_tags, now)
+            .save(path)
+            .map_err(Error::PlanFileWriteError)?;
+    }
+
+    if options.dry_run || options.plan_file.is_some() {
+        return match failed_targets {
+            0 => Ok(()),
+            n => Err(Error::FailedTargets(n)),
+        };
+    }
+
+    if plans.len() > 1 && !options.quiet {
+        print_deletion_summary(&plans);
+    }
+
+    let tag_count: usize = plans.iter().map(|p| p.plan.tags().len()).sum();
+    let phrase = if tag_count > options.confirm_threshold {
+        Some(confirmation_phrase(&plans, &namespaces))
+    } else {
+        None
+    };
+
+    if confirm_deletion(
+        options.assume_yes,
+        io::stdin().is_terminal(),
+        phrase.as_deref(),
+    )? {
+        if let Some(command) = options.pre_delete_hook.as_deref() {
+            let plan = PlanFile::new(&options.region, &plan_tags, now);
+
+            run_pre_delete_hook(command, &plan).await?;
+        }
+
+        let pb = if !show_progress {
+            ProgressBar::hidden()
+        } else {
+            let ps = ProgressStyle::default_bar().template(PROGRESS_TEMPLATE);
+            let deletions: usize = plans.iter().map(|p| p.plan.deletions.len()).sum();
+
+            ProgressBar::new(deletions as u64).with_style(ps)
+        };
+
+        if plans.len() == 1 {
+            pb.set_prefix(plans[0].image.name());
+        } else {
+            pb.set_prefix(&format!("{} images", plans.len()));
+        }
+
+        let interrupt = Interrupt::default();
+
+        interrupt.listen_for_ctrl_c();
+
+        let mut report = prune(
+            &registry,
+            &plans,
+            options.concurrency,
+            &pb,
+            rate_limiter.as_ref(),
+            resume_log.as_ref(),
+            &interrupt,
+        )
+        .await;
+
+        interrupt.disarm();
+        pb.finish();
+
+        if options.wait && !report.deleted.is_empty() {
+            let timeout = options.wait_timeout.to_std().unwrap();
+            let pb = if !show_progress {
+                ProgressBar::hidden()
+            } else {
+                let ps = ProgressStyle::default_bar().template(PROGRESS_TEMPLATE);
+
+                ProgressBar::new(report.deleted.len() as u64).with_style(ps)
+            };
+
+            pb.set_prefix("Waiting for the deletions");
+
+            let pending = wait_for_deletions(&registry, &report.deleted, timeout, &pb).await;
+
+            pb.finish();
+            report.fail_deleted(
+                &pending,
+                &format!(
+                    "still being deleted after {}",
+                    humantime::format_duration(timeout)
+                ),
+            );
+        }
+
+        if !options.quiet {
+            for (image, tag) in report.changed.iter() {
+                let line = format!(
+                    "Skipped {}:{}: it was pushed again after the plan was made",
+                    image.name(),
+                    tag.name()
+                );
+
+                eprintln!("{}", style(line).dim());
+            }
+        }
+
+        let mut reclaimed = None;
+
+        for p in plans.iter() {
+            let image_deleted = report.deleted_tags_of(p.image).len();
+
+            if let Some(size) = estimate_reclaimed_size(p.image.size(), p.tags.len(), image_deleted)
+            {
+                reclaimed = Some(reclaimed.unwrap_or(0) + size);
+            }
+        }
+
+        if !options.quiet {
+            eprintln!(
+                "{} in {:.1}s",
+                reclaimed_summary(report.deleted.len(), reclaimed),
+                report.elapsed.as_secs_f64()
+            );
+        }
+
+        *metrics = report_metrics(&report, reclaimed);
+
+        // The remaining steps are skipped when interrupted, so the run stops as soon as possible
+        if !report.interrupted.is_empty() {
+            print_failed_deletions(&report);
+
+            return Err(Error::Interrupted(report.interrupted.len()));
+        }
+
+        if options.verify {
+            let mut verified = 0;
+            let mut remaining = 0;
+
+            for p in plans.iter() {
+                let deleted = report.deleted_tags_of(p.image);
+
+                if deleted.is_empty() {
+                    continue;
+                }
+
+                match registry.image_tags_all(p.image.id()).await {
+                    Ok(current) => {
+                        let remaining_tags = remaining_tags(&deleted, &current);
+
+                        for tag in remaining_tags.iter() {
+                            eprintln!(
+                                "Warning: {}:{} still exists after being deleted",
+                                p.image.name(),
+                                tag.name()
+                            );
+                        }
+
+                        verified += deleted.len() - remaining_tags.len();
+                        remaining += remaining_tags.len();
+                    }
+                    Err(err) => eprintln!(
+                        "Unable to verify the deletions of {}: {}",
+                        p.image.name(),
+                        err
+                    ),
+                }
+            }
+
+            if !options.quiet {
+                eprintln!(
+                    "Verified {} deleted tags, {} still present",
+                    verified, remaining
+                );
+            }
+        }
+
+        let mut failed_images = 0;
+
+        if options.delete_empty_images {
+            let mut empty_images = Vec::new();
+
+            for p in plans.iter() {
+                if report.deleted_tags_of(p.image).is_empty() {
+                    continue;
+                }
+
+                match registry.image_tags_all(p.image.id()).await {
+                    Ok(tags) if !has_remaining_tags(&tags) => empty_images.push(p.image),
+                    Ok(_) => {}
+                    Err(err) => eprintln!(
+                        "Unable to list the remaining tags of {}: {}",
+                        p.image.name(),
+                        err
+                    ),
+                }
+            }
+
+            if !empty_images.is_empty() {
+                if !options.quiet {
+                    println!("This will delete the following images that have no tags left:");
+
+                    for image in empty_images.iter() {
+                        println!("  {}", image.name());
+                    }
+                }
+
+                if confirm_deletion(options.assume_yes, io::stdin().is_terminal(), None)? {
+                    for image in empty_images {
+                        if let Err(err) = registry.delete_image(image.id()).await {
+                            eprintln!("Failed to delete {}: {}", image.name(), err);
+                            failed_images += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        if !report.failed.is_empty() || failed_images > 0 {
+            print_failed_deletions(&report);
+
+            return Err(Error::FailedDeletions(report.failed.len() + failed_images));
+        }
+    }
+
+    match failed_targets {
+        0 => Ok(()),
+        n => Err(Error::FailedTargets(n)),
+    }
+}
+
+fn main() {
+    use tokio::runtime::Runtime;
+    let rt = Runtime::new().expect("unable to create async runtime");
+
+    if let Err(e) = rt.block_on(try_main()) {
+        eprintln!("There was an error: {}", e);
+
+        std::process::exit(e.exit_code());
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
 
+    use chrono::TimeZone;
+
+    /// Returns the `Options` parsed from the given command-line `args`
+    fn options_from_args(args: &[&str]) -> Options {
+        let base = [
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "--scw-token",
+            "abc",
+        ];
+        let args = base.iter().chain(args).chain(&["ns/img"]);
+
+        parse_args(build_app().get_matches_from(args), &Profile::default()).unwrap()
+    }
+
+    #[test]
+    fn it_reads_targets_skipping_comments_and_blank_lines() {
+        let input = "# Inventory\nns/img\n\n  other  \n# ns/skipped\n";
+        let targets = read_targets(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            targets,
+            [
+                Target {
+                    namespace: "ns".to_owned(),
+                    image: Some("img".to_owned()),
+                },
+                Target {
+                    namespace: "other".to_owned(),
+                    image: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_the_line_of_a_malformed_target() {
+        let input = "ns/img\n# comment\n/img\n";
+
+        match read_targets(input.as_bytes()) {
+            Err(Error::MalformedTarget { line, content }) => {
+                assert_eq!(line, 3);
+                assert_eq!(content, "/img");
+            }
+            other => panic!("expected a malformed target, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_parses_image_argument() {
+        assert!(parse_image_argument("mynamespace/myimage").is_some());
+        assert!(parse_image_argument("").is_none());
+    }
+
+    #[test]
+    fn it_doesnt_parse_empty_namespace_or_image() {
+        assert!(parse_image_argument("/myimage").is_none());
+        assert!(parse_image_argument("mynamespace/").is_none());
+    }
+
+    #[test]
+    fn it_parses_namespace_and_image() {
+        let res = parse_image_argument("mynamespace/myimage");
+
+        assert_eq!(res.unwrap().0, "mynamespace");
+        assert_eq!(res.unwrap().1, Some("myimage"));
+    }
+
+    #[test]
+    fn it_parses_the_image_argument_into_a_target() {
+        assert_eq!(
+            options_from_args(&[]).targets,
+            [Target {
+                namespace: "ns".to_owned(),
+                image: Some("img".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn it_parses_multiple_image_arguments() {
+        let targets = options_from_args(&["ns/app", "other"])
+            .targets
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(targets, ["ns/app", "other", "ns/img"]);
+    }
+
+    #[test]
+    fn it_resolves_image_visibility() {
+        let list: serde_json::Value = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/image_list_mixed_visibility.json"
+        ))
+        .unwrap();
+        let images = list["images"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|image| serde_json::from_str::<Image>(&image.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        let private_namespace = test_helpers::namespace("ns");
+        let visibilities = images
+            .iter()
+            .map(|image| image_visibility(image, &private_namespace))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            visibilities,
+            [Visibility::Public, Visibility::Private, Visibility::Private]
+        );
+    }
+
+    #[test]
+    fn it_parses_visibility() {
+        assert_eq!(options_from_args(&[]).visibility, None);
+        assert_eq!(
+            options_from_args(&["--visibility", "private"]).visibility,
+            Some(Visibility::Private)
+        );
+    }
+
+    #[test]
+    fn it_finds_images_in_namespace() {
+        let namespaces = vec![test_helpers::namespace("ns")];
+        let images = vec![test_helpers::image("app"), test_helpers::image("worker")];
+
+        let (_, found) =
+            find_namespace_and_images(&namespaces, &images, "ns", Some("app")).unwrap();
+        assert_eq!(found.iter().map(|i| i.name()).collect::<Vec<_>>(), ["app"]);
+
+        let (_, found) = find_namespace_and_images(&namespaces, &images, "ns", None).unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert!(matches!(
+            find_namespace_and_images(&namespaces, &images, "ns", Some("missing")),
+            Err(Error::NoSuchImage)
+        ));
+        assert!(matches!(
+            find_namespace_and_images(&namespaces, &images, "missing", None),
+            Err(Error::NoSuchNamespace)
+        ));
+    }
+
     #[test]
-    fn it_parses_image_argument() {
-        assert!(parse_image_argument("mynamespace/myimage").is_some());
-        assert!(parse_image_argument("mynamespace").is_none());
+    fn it_finds_images_with_nested_names() {
+        let namespaces = vec![test_helpers::namespace("ns")];
+        let images = vec![test_helpers::image("team/app"), test_helpers::image("app")];
+
+        let (_, found) =
+            find_namespace_and_images(&namespaces, &images, "ns", Some("team/app")).unwrap();
+        assert_eq!(
+            found.iter().map(|i| i.name()).collect::<Vec<_>>(),
+            ["team/app"]
+        );
     }
 
     #[test]
-    fn it_doesnt_parse_empty_namespace_or_image() {
-        assert!(parse_image_argument("/myimage").is_none());
-        assert!(parse_image_argument("mynamespace/").is_none());
+    fn it_finds_namespaces_by_name_or_id() {
+        let namespaces = vec![test_helpers::namespace("ns")];
+        let images = vec![test_helpers::image("app")];
+
+        let (namespace, _) =
+            find_namespace_and_images(&namespaces, &images, "ns", Some("app")).unwrap();
+        assert_eq!(namespace.name(), "ns");
+
+        let (namespace, found) = find_namespace_and_images(
+            &namespaces,
+            &images,
+            "1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4",
+            Some("app"),
+        )
+        .unwrap();
+        assert_eq!(namespace.name(), "ns");
+        assert_eq!(found.len(), 1);
+        assert!(parse_image_argument("1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4/app").is_some());
     }
 
     #[test]
-    fn it_parses_namespace_and_image() {
-        let res = parse_image_argument("mynamespace/myimage");
+    fn it_parses_nested_image_names() {
+        assert_eq!(
+            parse_image_argument("mynamespace/team/app"),
+            Some(("mynamespace", Some("team/app")))
+        );
+        assert!(validate_image_arg("mynamespace/team/app".to_owned()).is_ok());
+    }
 
-        assert_eq!(res.unwrap().0, "mynamespace");
-        assert_eq!(res.unwrap().1, "myimage");
+    #[test]
+    fn it_doesnt_parse_empty_segments_of_nested_image_names() {
+        assert!(parse_image_argument("mynamespace//app").is_none());
+        assert!(parse_image_argument("mynamespace/team/").is_none());
+        assert!(parse_image_argument("mynamespace/team//app").is_none());
+        assert!(validate_image_arg("mynamespace/team/".to_owned()).is_err());
+    }
+
+    #[test]
+    fn it_parses_namespace_only() {
+        assert_eq!(
+            parse_image_argument("mynamespace"),
+            Some(("mynamespace", None))
+        );
+    }
+
+    #[test]
+    fn it_accepts_namespace_only_image_argument() {
+        assert!(validate_image_arg("mynamespace".to_owned()).is_ok());
+        assert!(validate_image_arg("mynamespace/".to_owned()).is_err());
+    }
+
+    #[test]
+    fn it_parses_dry_run_flag() {
+        assert!(!options_from_args(&[]).dry_run);
+        assert!(options_from_args(&["--dry-run"]).dry_run);
+    }
+
+    #[test]
+    fn it_formats_tag_ages_in_days() {
+        assert_eq!(format_age(Duration::days(42)), "42 days old");
+        assert_eq!(format_age(Duration::hours(30)), "1 day old");
+        assert_eq!(format_age(Duration::hours(-5)), "0 days old");
+    }
+
+    #[test]
+    fn it_estimates_reclaimed_size_from_image_size() {
+        assert_eq!(estimate_reclaimed_size(1000, 10, 3), Some(300));
+        assert_eq!(estimate_reclaimed_size(0, 10, 3), None);
+    }
+
+    #[test]
+    fn it_summarizes_the_counts() {
+        assert_eq!(
+            count_summary(3, 10),
+            "Would delete 3 of 10 tags (keeping 7)"
+        );
+        assert_eq!(count_summary(0, 0), "Would delete 0 of 0 tags (keeping 0)");
+    }
+
+    #[test]
+    fn it_summarizes_reclaimed_size() {
+        assert_eq!(
+            reclaimed_summary(3, Some(3 * 1024 * 1024)),
+            "Reclaimed approximately 3.0 MiB across 3 tags"
+        );
+        assert_eq!(reclaimed_summary(3, None), "Deleted 3 tags");
+    }
+
+    #[test]
+    fn it_protects_latest_unless_opted_out() {
+        assert!(options_from_args(&[]).filter.protect_latest);
+        assert!(
+            !options_from_args(&["--no-protect-latest"])
+                .filter
+                .protect_latest
+        );
+        assert!(
+            options_from_args(&["--no-protect-latest", "--protect-latest"])
+                .filter
+                .protect_latest
+        );
+    }
+
+    #[test]
+    fn it_parses_cache_options() {
+        let options = options_from_args(&[]);
+
+        assert_eq!(options.cache, None);
+        assert_eq!(options.cache_ttl, Duration::minutes(5));
+
+        let options = options_from_args(&["--cache", "/tmp/cache.json", "--cache-ttl", "1h"]);
+
+        assert_eq!(options.cache, Some(PathBuf::from("/tmp/cache.json")));
+        assert_eq!(options.cache_ttl, Duration::hours(1));
+    }
+
+    #[test]
+    fn it_parses_list_flag() {
+        assert!(!options_from_args(&[]).list);
+        assert!(options_from_args(&["--list"]).list);
+    }
+
+    #[test]
+    fn it_rejects_retention_rules_with_apply_plan() {
+        let apply_plan = |args: &[&str]| {
+            let base = ["scaleway-registry-prune", "--apply-plan", "plan.json"];
+
+            build_app().get_matches_from_safe(base.iter().chain(args))
+        };
+
+        assert!(apply_plan(&[]).is_ok());
+
+        for args in [
+            &["--exclude", "latest"][..],
+            &["--by", "created"],
+            &["--no-protect-latest"],
+            &["--prune-non-semver"],
+        ] {
+            assert!(apply_plan(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn it_parses_count_only_flag() {
+        assert!(!options_from_args(&[]).count_only);
+        assert!(options_from_args(&["--count-only"]).count_only);
+    }
+
+    #[test]
+    fn it_parses_wait_options() {
+        let options = options_from_args(&[]);
+
+        assert!(!options.wait);
+        assert_eq!(options.wait_timeout, Duration::minutes(5));
+
+        let options = options_from_args(&["--wait", "--wait-timeout", "90s"]);
+
+        assert!(options.wait);
+        assert_eq!(options.wait_timeout, Duration::seconds(90));
+    }
+
+    #[test]
+    fn it_parses_force_shared_digests_flag() {
+        assert!(!options_from_args(&[]).force_shared_digests);
+        assert!(options_from_args(&["--force-shared-digests"]).force_shared_digests);
+    }
+
+    #[test]
+    fn it_skips_confirmation_when_yes_is_given() {
+        let options = options_from_args(&["-y"]);
+
+        assert!(options.assume_yes);
+        assert!(confirm_deletion(options.assume_yes, false, None).unwrap());
+    }
+
+    #[test]
+    fn it_parses_durations() {
+        assert_eq!(parse_duration("90m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("3d"), Ok(Duration::days(3)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::weeks(2)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::seconds(45)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::hours(12)));
+    }
+
+    #[test]
+    fn it_parses_date_times() {
+        assert_eq!(
+            parse_date_time("2020-04-01T12:30:00+02:00"),
+            Ok(Utc.ymd(2020, 4, 1).and_hms(10, 30, 0))
+        );
+        assert_eq!(
+            parse_date_time("2020-04-01"),
+            Ok(Utc.ymd(2020, 4, 1).and_hms(0, 0, 0))
+        );
+        assert!(parse_date_time("yesterday").is_err());
+        assert!(validate_date_time("2020-13-01".to_owned()).is_err());
+    }
+
+    #[test]
+    fn it_doesnt_parse_invalid_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("3").is_err());
+        assert!(parse_duration("d3").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+    }
+
+    #[test]
+    fn it_parses_retention_rules() {
+        let options = options_from_args(&[
+            "--keep-last",
+            "5",
+            "--keep-last-digests",
+            "2",
+            "--keep-last-semver",
+            "3",
+            "--keep-daily",
+            "7",
+            "--keep-weekly",
+            "4",
+            "--keep-monthly",
+            "6",
+            "--keep-within",
+            "3d",
+            "--min-keep",
+            "1",
+            "--older-than",
+            "2020-04-01",
+            "--by",
+            "created",
+        ]);
+
+        assert_eq!(options.filter.keep_last, Some(5));
+        assert_eq!(options.filter.keep_last_digests, Some(2));
+        assert_eq!(options.filter.keep_last_semver, Some(3));
+        assert_eq!(options.filter.keep_daily, Some(7));
+        assert_eq!(options.filter.keep_weekly, Some(4));
+        assert_eq!(options.filter.keep_monthly, Some(6));
+        assert_eq!(options.filter.keep_within, Some(Duration::days(3)));
+        assert_eq!(options.filter.min_keep, 1);
+        assert_eq!(options.filter.age_by, filter::AgeField::Created);
+        assert_eq!(
+            options.filter.older_than,
+            Some(Utc.ymd(2020, 4, 1).and_hms(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_match_patterns() {
+        assert!(validate_regex(r"^pr-\d+$".to_owned()).is_ok());
+        assert!(validate_regex("pr-(".to_owned()).is_err());
+
+        let res = build_app().get_matches_from_safe([
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "--scw-token",
+            "abc",
+            "--match",
+            "pr-(",
+            "ns/img",
+        ]);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn it_parses_repeated_excludes() {
+        let options = options_from_args(&["--exclude", "latest", "--exclude", "prod-*"]);
+        let exclude = options.filter.exclude.unwrap();
+
+        assert!(exclude.is_match("latest"));
+        assert!(exclude.is_match("prod-eu"));
+        assert!(!exclude.is_match("a1b2c3d"));
+        assert!(validate_glob("[".to_owned()).is_err());
+    }
+
+    #[test]
+    fn it_parses_output_format() {
+        assert_eq!(options_from_args(&[]).output, OutputFormat::Text);
+        assert_eq!(
+            options_from_args(&["--output", "json"]).output,
+            OutputFormat::Json
+        );
+        assert_eq!(
+            options_from_args(&["--output", "table"]).output,
+            OutputFormat::Table
+        );
+        assert_eq!(
+            options_from_args(&["--output", "csv"]).output,
+            OutputFormat::Csv
+        );
+    }
+
+    #[test]
+    fn it_parses_color_choice() {
+        assert_eq!(options_from_args(&[]).color, ColorChoice::Auto);
+        assert_eq!(
+            options_from_args(&["--color", "never"]).color,
+            ColorChoice::Never
+        );
+    }
+
+    #[test]
+    fn it_only_colors_terminals_without_no_color() {
+        assert!(ColorChoice::Auto.is_enabled(true, false));
+        assert!(!ColorChoice::Auto.is_enabled(false, false));
+        assert!(!ColorChoice::Auto.is_enabled(true, true));
+        assert!(ColorChoice::Always.is_enabled(false, true));
+        assert!(!ColorChoice::Never.is_enabled(true, false));
+    }
+
+    #[test]
+    fn it_describes_the_namespace_in_the_plan_header() {
+        let namespace: Namespace = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/namespace.json"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            format_namespace_header(&namespace),
+            "Namespace mynamespace (private, 3 images)"
+        );
+
+        let namespace: Namespace = serde_json::from_str(
+            &include_str!("../../scaleway_sdk/tests/fixtures/namespace.json")
+                .replace(r#""is_public": false"#, r#""is_public": true"#)
+                .replace(r#""description": """#, r#""description": "Production""#),
+        )
+        .unwrap();
+
+        assert_eq!(
+            format_namespace_header(&namespace),
+            "Namespace mynamespace (public, 3 images): Production"
+        );
+    }
+
+    #[test]
+    fn it_formats_the_deletion_plan_as_csv() {
+        let image = test_helpers::image("my,image");
+        let tag: ImageTag = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/image_tag.json"
+        ))
+        .unwrap();
+        let csv = format_deletion_csv(&[(&image, vec![&tag])]);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            [
+                "image,tag,digest,created_at,updated_at,id",
+                "\"my,image\",latest,\
+                 sha256:1675dda591024811b7f7e67451cab2a4d85967475234285ec8eabaebb508f45e,\
+                 2019-08-28T12:52:30.621274Z,2020-04-14T21:23:48.831047Z,\
+                 5e3b5f16-afe2-44a4-93d5-3cbebee14960"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_sort_options() {
+        let options = options_from_args(&[]);
+
+        assert_eq!(options.sort, SortField::Updated);
+        assert!(!options.reverse);
+
+        let options = options_from_args(&["--sort", "name", "--reverse"]);
+
+        assert_eq!(options.sort, SortField::Name);
+        assert!(options.reverse);
+    }
+
+    /// Returns tags named `b`, `c` and `a`, where `c` was created first but `b` updated last
+    fn unsorted_tags() -> Vec<ImageTag> {
+        let now = Utc::now();
+        vec![
+            test_helpers::image_tag("b", now),
+            test_helpers::image_tag_created_at(
+                "c",
+                now - Duration::days(9),
+                now - Duration::days(1),
+            ),
+            test_helpers::image_tag("a", now - Duration::days(2)),
+        ]
+    }
+
+    #[test]
+    fn it_sorts_tags_by_updated_at() {
+        let mut tags = unsorted_tags();
+
+        sort_tags(&mut tags, SortField::Updated, false);
+        assert_eq!(
+            tags.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["b", "c", "a"]
+        );
+
+        sort_tags(&mut tags, SortField::Updated, true);
+        assert_eq!(
+            tags.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["a", "c", "b"]
+        );
+    }
+
+    #[test]
+    fn it_sorts_tags_by_created_at() {
+        let mut tags = unsorted_tags();
+
+        sort_tags(&mut tags, SortField::Created, false);
+        assert_eq!(
+            tags.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["b", "a", "c"]
+        );
+    }
+
+    #[test]
+    fn it_sorts_tags_by_name() {
+        let tags = unsorted_tags();
+        let mut tag_refs = tags.iter().collect::<Vec<&ImageTag>>();
+
+        sort_tags(&mut tag_refs, SortField::Name, false);
+        assert_eq!(
+            tag_refs.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+
+        sort_tags(&mut tag_refs, SortField::Name, true);
+        assert_eq!(
+            tag_refs.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["c", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn it_formats_deletion_plan_as_table() {
+        let updated_at = "2020-04-14T21:23:48Z".parse::<DateTime<Utc>>().unwrap();
+        let image = test_helpers::image("myimage");
+        let tags = [
+            test_helpers::image_tag_with_digest("1.0", "sha256:0123456789abcdef", updated_at),
+            test_helpers::image_tag_with_digest(
+                "feature-long-branch-name",
+                "sha256:fedcba9876543210",
+                updated_at - Duration::days(40),
+            ),
+        ];
+        let plans = vec![(&image, tags.iter().collect::<Vec<_>>())];
+        let now = updated_at + Duration::days(2);
+
+        assert_eq!(
+            format_deletion_table(&plans, now),
+            "\
+TAG                               DIGEST               UPDATED              AGE
+myimage:1.0                       sha256:0123456789ab  2020-04-14 21:23:48  2 days old
+myimage:feature-long-branch-name  sha256:fedcba987654  2020-03-05 21:23:48  42 days old
+"
+        );
+    }
+
+    #[test]
+    fn it_serializes_planned_deletions() {
+        let image = test_helpers::image("myimage");
+        let tag = test_helpers::image_tag("a1b2c3d", "2020-04-14T21:23:48Z".parse().unwrap());
+        let json = serde_json::to_value(PlannedDeletion::new(&image, &tag)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "image": "myimage",
+                "tag": "a1b2c3d",
+                "digest": "sha256:a1b2c3d",
+                "updated_at": "2020-04-14T21:23:48Z",
+                "id": "a1b2c3d-id",
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_concurrency() {
+        assert_eq!(options_from_args(&[]).concurrency, 4);
+        assert_eq!(options_from_args(&["--concurrency", "8"]).concurrency, 8);
+        assert_eq!(options_from_args(&[]).image_concurrency, 4);
+        assert_eq!(
+            options_from_args(&["--image-concurrency", "2"]).image_concurrency,
+            2
+        );
+        assert!(validate_parsable::<NonZeroUsize>("0".to_owned()).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_keeps_deleting_after_a_failed_deletion() {
+        let now = Utc::now();
+        let tags = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| test_helpers::image_tag(name, now))
+            .collect::<Vec<_>>();
+        let deleted = std::sync::Mutex::new(Vec::new());
+        let pb = ProgressBar::hidden();
+
+        let (failed, interrupted) =
+            delete_image_tags(&tags, 2, &pb, &Interrupt::default(), |tag| {
+                let deleted = &deleted;
+
+                async move {
+                    if tag.name() == "b" {
+                        Err("boom")
+                    } else {
+                        deleted.lock().unwrap().push(tag.name().to_owned());
+                        Ok(tag)
+                    }
+                }
+            })
+            .await;
+
+        let mut deleted = deleted.into_inner().unwrap();
+        deleted.sort();
+
+        assert_eq!(deleted, ["a", "c", "d"]);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.name(), "b");
+        assert!(interrupted.is_empty());
+        assert_eq!(pb.position(), 4);
+    }
+
+    #[tokio::test]
+    async fn it_stops_starting_deletions_once_interrupted() {
+        let now = Utc::now();
+        let tags = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| test_helpers::image_tag(name, now))
+            .collect::<Vec<_>>();
+        let interrupt = Interrupt::default();
+        let pb = ProgressBar::hidden();
+
+        // The first deletion is interrupted halfway through, while the second one is in flight
+        let (failed, interrupted) = delete_image_tags(&tags, 2, &pb, &interrupt, |tag| {
+            let interrupt = &interrupt;
+
+            async move {
+                if tag.name() == "a" {
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    interrupt.set();
+                } else {
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                }
+
+                Ok::<_, &str>(tag)
+            }
+        })
+        .await;
+
+        assert!(failed.is_empty());
+        assert_eq!(
+            interrupted.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["c", "d", "e"]
+        );
+        assert_eq!(pb.position(), 2);
+    }
+
+    #[test]
+    fn it_reports_unauthorized_responses_as_authentication_failures() {
+        let err = Error::from(scaleway_sdk::Error::Http {
+            status: scaleway_sdk::StatusCode::UNAUTHORIZED,
+            message: "authentication is denied".to_owned(),
+            request_id: None,
+        });
+
+        assert!(matches!(err, Error::AuthenticationFailed));
+    }
+
+    #[test]
+    fn it_reports_forbidden_responses_as_access_denied() {
+        let err = Error::from(scaleway_sdk::Error::Http {
+            status: scaleway_sdk::StatusCode::FORBIDDEN,
+            message: "insufficient permissions".to_owned(),
+            request_id: None,
+        });
+
+        assert!(matches!(err, Error::AccessDenied));
+        assert_eq!(err.exit_code(), 4);
+    }
+
+    #[test]
+    fn it_keeps_sdk_errors_as_the_error_source() {
+        let err = Error::from(scaleway_sdk::Error::Http {
+            status: scaleway_sdk::StatusCode::NOT_FOUND,
+            message: "resource is not found".to_owned(),
+            request_id: None,
+        });
+        let source = std::error::Error::source(&err).unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "SDK error: API error (404 Not Found): resource is not found"
+        );
+        assert_eq!(
+            source.to_string(),
+            "API error (404 Not Found): resource is not found"
+        );
+    }
+
+    #[test]
+    fn it_accepts_secret_key_instead_of_token() {
+        let args = [
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "--secret-key",
+            "secret",
+            "ns/img",
+        ];
+        let options = parse_args(build_app().get_matches_from(args), &Profile::default()).unwrap();
+
+        assert_eq!(options.token, "secret");
+    }
+
+    #[test]
+    fn it_prefers_token_over_secret_key() {
+        let options = options_from_args(&["--secret-key", "secret"]);
+
+        assert_eq!(options.token, "abc");
+    }
+
+    #[test]
+    fn it_falls_back_to_config_profile() {
+        let profile = Profile {
+            secret_key: Some("secret".to_owned()),
+            default_region: Some("fr-par".to_owned()),
+        };
+        let matches = build_app().get_matches_from(["scaleway-registry-prune", "ns/img"]);
+        let options = parse_args(matches, &profile).unwrap();
+
+        assert_eq!(options.token, "secret");
+        assert_eq!(options.region, "fr-par");
+    }
+
+    #[test]
+    fn it_validates_the_region_of_the_config_profile() {
+        let profile = Profile {
+            secret_key: Some("secret".to_owned()),
+            default_region: Some("nl-amz".to_owned()),
+        };
+        let matches = build_app().get_matches_from(["scaleway-registry-prune", "ns/img"]);
+
+        match parse_args(matches, &profile) {
+            Err(Error::InvalidConfigRegion(message)) => {
+                assert!(message.contains("did you mean `nl-ams'"), "{}", message)
+            }
+            res => panic!("unexpected result: {:?}", res.map(|o| o.region)),
+        }
+    }
+
+    #[test]
+    fn it_only_needs_the_config_without_region_or_credentials() {
+        let needs_config = |args: &[&str]| {
+            let args = std::iter::once("scaleway-registry-prune")
+                .chain(args.iter().copied())
+                .chain(std::iter::once("ns/img"));
+
+            needs_config(&build_app().get_matches_from(args))
+        };
+
+        assert!(needs_config(&[]));
+        assert!(needs_config(&["--region", "nl-ams"]));
+        assert!(needs_config(&["--scw-token", "abc"]));
+        assert!(!needs_config(&["--region", "nl-ams", "--scw-token", "abc"]));
+        assert!(!needs_config(&[
+            "--region",
+            "nl-ams",
+            "--secret-key",
+            "abc"
+        ]));
+    }
+
+    #[test]
+    fn it_prefers_arguments_over_config_profile() {
+        let profile = Profile {
+            secret_key: Some("secret".to_owned()),
+            default_region: Some("fr-par".to_owned()),
+        };
+        let matches = build_app().get_matches_from([
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "--scw-token",
+            "abc",
+            "ns/img",
+        ]);
+        let options = parse_args(matches, &profile).unwrap();
+
+        assert_eq!(options.token, "abc");
+        assert_eq!(options.region, "nl-ams");
+    }
+
+    #[test]
+    fn it_rejects_retention_rules_that_keep_nothing() {
+        let matches = build_app().get_matches_from([
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "--scw-token",
+            "token",
+            "--keep-last",
+            "0",
+            "ns/img",
+        ]);
+
+        assert!(matches!(
+            parse_args(matches, &Profile::default()),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn it_requires_region_and_credentials() {
+        let matches = build_app().get_matches_from(["scaleway-registry-prune", "ns/img"]);
+
+        assert!(matches!(
+            parse_args(matches, &Profile::default()),
+            Err(Error::MissingRegion)
+        ));
+
+        let matches = build_app().get_matches_from([
+            "scaleway-registry-prune",
+            "--region",
+            "nl-ams",
+            "ns/img",
+        ]);
+
+        assert!(matches!(
+            parse_args(matches, &Profile::default()),
+            Err(Error::MissingCredentials)
+        ));
+    }
+
+    #[test]
+    fn it_renders_progress_templates() {
+        let pb = ProgressBar::hidden()
+            .with_style(ProgressStyle::default_bar().template(PROGRESS_TEMPLATE));
+
+        pb.set_length(10);
+        pb.inc(1);
+        pb.finish();
+
+        let spinner = ProgressBar::hidden()
+            .with_style(ProgressStyle::default_spinner().template(SPINNER_TEMPLATE));
+
+        spinner.set_message("Listing namespaces and images");
+        spinner.finish_and_clear();
+    }
+
+    #[test]
+    fn it_returns_deleted_tags_that_are_still_ready() {
+        let now = Utc::now();
+        let deleted = [
+            test_helpers::image_tag("a", now),
+            test_helpers::image_tag("b", now),
+            test_helpers::image_tag("c", now),
+        ];
+        let deleted = deleted.iter().collect::<Vec<_>>();
+        let current = [
+            test_helpers::image_tag("b", now),
+            test_helpers::image_tag("d", now),
+        ];
+
+        assert_eq!(
+            remaining_tags(&deleted, &current)
+                .iter()
+                .map(|t| t.name())
+                .collect::<Vec<_>>(),
+            ["b"]
+        );
+    }
+
+    #[test]
+    fn it_considers_images_with_only_deleted_tags_empty() {
+        let tags: serde_json::Value = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/image_tag_list_mixed_status.json"
+        ))
+        .unwrap();
+        let tags = tags["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tag| serde_json::from_str::<ImageTag>(&tag.to_string()).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(has_remaining_tags(&tags));
+        assert!(!has_remaining_tags(&tags[1..2]));
+        assert!(!has_remaining_tags(&[]));
+    }
+
+    #[test]
+    fn it_validates_endpoints() {
+        assert!(validate_endpoint("http://localhost:8080".to_owned()).is_ok());
+        assert!(validate_endpoint("https://api.example.com/registry/v1".to_owned()).is_ok());
+        assert!(validate_endpoint("localhost:8080".to_owned()).is_err());
+    }
+
+    #[test]
+    fn it_validates_regions() {
+        assert!(validate_region("fr-par".to_owned()).is_ok());
+        assert!(validate_region("nl-ams".to_owned()).is_ok());
+        assert!(validate_region("pl-waw".to_owned()).is_ok());
+        assert_eq!(
+            validate_region("fr-pr".to_owned()),
+            Err(
+                "Unknown region, expected one of fr-par, nl-ams, pl-waw, did you mean `fr-par'?"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            validate_region("us-east-1".to_owned()),
+            Err("Unknown region, expected one of fr-par, nl-ams, pl-waw".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_flags_limited_regions() {
+        assert!(region_limitation("pl-waw").is_some());
+        assert_eq!(region_limitation("fr-par"), None);
+        assert_eq!(region_limitation("nl-ams"), None);
+
+        for (region, _) in LIMITED_REGIONS.iter() {
+            assert!(REGIONS.contains(region), "{} isn't a known region", region);
+        }
+    }
+
+    #[test]
+    fn it_refuses_to_prompt_without_a_terminal() {
+        assert!(confirm_deletion(false, false, None).is_err());
+    }
+
+    #[test]
+    fn it_resolves_tags_by_name() {
+        let tags = unsorted_tags();
+        let names = ["a".to_owned(), "b".to_owned()];
+
+        assert_eq!(missing_tag(&tags, &names), None);
+        assert_eq!(
+            named_tags(&tags, &names)
+                .iter()
+                .map(|t| t.id())
+                .collect::<Vec<_>>(),
+            ["b-id", "a-id"]
+        );
+    }
+
+    #[test]
+    fn it_reports_tags_that_dont_exist() {
+        let tags = unsorted_tags();
+        let names = ["a".to_owned(), "nope".to_owned()];
+
+        assert_eq!(missing_tag(&tags, &names), Some("nope"));
+        assert_eq!(
+            options_from_args(&["--tag", "a", "--tag", "nope"]).tags,
+            names
+        );
+    }
+
+    #[test]
+    fn it_maps_the_interactive_selection_to_tags() {
+        let tags = unsorted_tags();
+        let selected = [&tags[0], &tags[2]];
+
+        assert_eq!(preselected_tags(&tags, &selected), [true, false, true]);
+        assert_eq!(
+            tags_at(&tags, &[1, 2])
+                .iter()
+                .map(|t| t.id())
+                .collect::<Vec<_>>(),
+            ["c-id", "a-id"]
+        );
+    }
+
+    #[test]
+    fn it_confirms_with_y_or_the_expected_phrase() {
+        assert!(is_confirmed("y", None));
+        assert!(is_confirmed("Y", None));
+        assert!(!is_confirmed("yes", None));
+        assert!(!is_confirmed("", None));
+
+        assert!(is_confirmed(
+            "mynamespace/myimage",
+            Some("mynamespace/myimage")
+        ));
+        assert!(!is_confirmed("y", Some("mynamespace/myimage")));
+        assert!(!is_confirmed(
+            "mynamespace/myimag",
+            Some("mynamespace/myimage")
+        ));
+        assert!(!is_confirmed(
+            "MyNamespace/MyImage",
+            Some("mynamespace/myimage")
+        ));
     }
 }
@@ -0,0 +1,152 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// The outcome of a prune run, as exported to node_exporter's textfile collector
+///
+/// The default metrics are those of a run that didn't delete anything.
+#[derive(Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub deleted: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// The estimated number of bytes freed by the deletions
+    pub reclaimed_bytes: u64,
+    /// How long the deletions took
+    pub duration: Duration,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl Metrics {
+    /// Returns the metrics in the Prometheus text exposition format
+    pub fn format(&self) -> String {
+        let metrics: [(&str, &str, &str, String); 6] = [
+            (
+                "scaleway_prune_deleted_total",
+                "gauge",
+                "The number of tags deleted by the last run",
+                self.deleted.to_string(),
+            ),
+            (
+                "scaleway_prune_failed_total",
+                "gauge",
+                "The number of tags the last run failed to delete",
+                self.failed.to_string(),
+            ),
+            (
+                "scaleway_prune_skipped_total",
+                "gauge",
+                "The number of tags selected by the last run but left alone",
+                self.skipped.to_string(),
+            ),
+            (
+                "scaleway_prune_reclaimed_bytes",
+                "gauge",
+                "The estimated storage freed by the last run",
+                self.reclaimed_bytes.to_string(),
+            ),
+            (
+                "scaleway_prune_duration_seconds",
+                "gauge",
+                "How long the deletions of the last run took",
+                format!("{:.3}", self.duration.as_secs_f64()),
+            ),
+            (
+                "scaleway_prune_last_run_timestamp_seconds",
+                "gauge",
+                "When the last run finished, in seconds since the Unix epoch",
+                self.finished_at.timestamp().to_string(),
+            ),
+        ];
+        let mut out = String::new();
+
+        for (name, kind, help, value) in metrics.iter() {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+            writeln!(out, "{} {}", name, value).unwrap();
+        }
+
+        out
+    }
+
+    /// Writes the metrics to the file at `path`
+    ///
+    /// The metrics are written to a temporary file next to it first, so that the collector never
+    /// reads a partially written file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, self.format())?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn metrics() -> Metrics {
+        Metrics {
+            deleted: 12,
+            failed: 1,
+            skipped: 2,
+            reclaimed_bytes: 104857600,
+            duration: Duration::from_millis(2500),
+            finished_at: "2020-04-14T21:23:48Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn it_formats_well_formed_metric_lines() {
+        let sample = Regex::new(r"^[a-zA-Z_:][a-zA-Z0-9_:]* [0-9]+(\.[0-9]+)?$").unwrap();
+        let formatted = metrics().format();
+
+        for line in formatted.lines().filter(|l| !l.starts_with('#')) {
+            assert!(sample.is_match(line), "malformed line: {}", line);
+        }
+
+        assert!(formatted.ends_with('\n'));
+    }
+
+    #[test]
+    fn it_describes_every_metric() {
+        let formatted = metrics().format();
+        let lines = formatted.lines().collect::<Vec<_>>();
+
+        for chunk in lines.chunks(3) {
+            let name = chunk[2].split(' ').next().unwrap();
+
+            assert!(chunk[0].starts_with(&format!("# HELP {} ", name)));
+            assert!(chunk[1].starts_with(&format!("# TYPE {} ", name)));
+        }
+    }
+
+    #[test]
+    fn it_formats_the_values() {
+        let formatted = metrics().format();
+
+        assert!(formatted.contains("\nscaleway_prune_deleted_total 12\n"));
+        assert!(formatted.contains("\nscaleway_prune_failed_total 1\n"));
+        assert!(formatted.contains("\nscaleway_prune_reclaimed_bytes 104857600\n"));
+        assert!(formatted.contains("\nscaleway_prune_duration_seconds 2.500\n"));
+        assert!(formatted.contains("\nscaleway_prune_last_run_timestamp_seconds 1586899428\n"));
+    }
+
+    #[test]
+    fn it_writes_the_metrics_file() {
+        let path = std::env::temp_dir().join(format!("scw-prune-{}.prom", std::process::id()));
+
+        metrics().write(&path).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, metrics().format());
+    }
+}
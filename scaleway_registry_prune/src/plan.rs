@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use scaleway_sdk::registry::{Image, ImageTag, Status};
+
+/// A single tag deletion request
+#[derive(Debug)]
+pub struct Deletion<'a> {
+    /// The tag to delete
+    pub tag: &'a ImageTag,
+    /// The other tags sharing the digest of `tag`, which the API deletes along with it when the
+    /// deletion is forced
+    pub shared: Vec<&'a ImageTag>,
+}
+
+impl<'a> Deletion<'a> {
+    /// Returns true if the deletion has to be forced because other tags share its digest
+    pub fn force(&self) -> bool {
+        !self.shared.is_empty()
+    }
+}
+
+/// The reason a tag selected for deletion is left alone
+#[derive(Debug, PartialEq)]
+pub enum SkipReason {
+    /// The digest of the tag is shared with a tag that is kept, which a forced deletion would
+    /// delete as well
+    SharedWithKeptTag,
+    /// The digest of the tag is shared with other tags, and deleting them together wasn't
+    /// allowed with `--force-shared-digests`
+    SharedDigest,
+    /// The tag isn't ready, e.g. because a previous run is still deleting it
+    NotReady(Status),
+    /// The tag was already deleted by a previous run according to the resume log
+    AlreadyDeleted,
+    /// The digest of the tag is referenced by a tag of the image with the given name
+    DigestInUse(String),
+}
+
+/// The deletions to perform for an image, along with the selected tags that are skipped
+#[derive(Debug, Default)]
+pub struct DeletionPlan<'a> {
+    pub deletions: Vec<Deletion<'a>>,
+    pub skipped: Vec<(&'a ImageTag, SkipReason)>,
+}
+
+impl<'a> DeletionPlan<'a> {
+    /// Returns every tag that is deleted when the plan is carried out
+    pub fn tags(&self) -> Vec<&'a ImageTag> {
+        self.deletions
+            .iter()
+            .flat_map(|d| std::iter::once(d.tag).chain(d.shared.iter().copied()))
+            .collect()
+    }
+
+    /// Returns true if the plan doesn't delete anything
+    pub fn is_empty(&self) -> bool {
+        self.deletions.is_empty()
+    }
+
+    /// Moves the deletions of tags that `is_deleted` says are already deleted to the skipped tags,
+    /// along with the tags sharing their digest
+    pub fn skip_deleted(&mut self, is_deleted: impl Fn(&ImageTag) -> bool) {
+        let (deleted, remaining) = std::mem::take(&mut self.deletions)
+            .into_iter()
+            .partition::<Vec<_>, _>(|d| is_deleted(d.tag));
+
+        self.deletions = remaining;
+        self.skipped.extend(deleted.into_iter().flat_map(|d| {
+            std::iter::once(d.tag)
+                .chain(d.shared)
+                .map(|tag| (tag, SkipReason::AlreadyDeleted))
+        }));
+    }
+
+    /// Moves the deletions of digests that `used_by` says are referenced by another image to the
+    /// skipped tags, with the name of that image
+    pub fn skip_digests_in_use(&mut self, used_by: impl Fn(&str) -> Option<String>) {
+        let mut remaining = Vec::with_capacity(self.deletions.len());
+
+        for deletion in std::mem::take(&mut self.deletions) {
+            match used_by(deletion.tag.digest()) {
+                Some(image) => self.skipped.extend(
+                    std::iter::once(deletion.tag)
+                        .chain(deletion.shared)
+                        .map(|tag| (tag, SkipReason::DigestInUse(image.clone()))),
+                ),
+                None => remaining.push(deletion),
+            }
+        }
+
+        self.deletions = remaining;
+    }
+}
+
+/// Returns the images referencing each digest among the tags of the given `images`
+pub fn index_digests<'a>(
+    images: impl IntoIterator<Item = (&'a Image, &'a [ImageTag])>,
+) -> HashMap<&'a str, Vec<&'a Image>> {
+    let mut index: HashMap<&str, Vec<&Image>> = HashMap::new();
+
+    for (image, tags) in images {
+        for tag in tags {
+            let users = index.entry(tag.digest()).or_default();
+
+            if users.iter().all(|i| i.id() != image.id()) {
+                users.push(image);
+            }
+        }
+    }
+
+    index
+}
+
+/// Returns the deletions needed to delete the `selected` tags out of all the `image_tags`
+///
+/// The API refuses to delete a tag that shares its digest with other tags unless the deletion is
+/// forced, in which case every tag sharing the digest is deleted. Because of this, selected tags
+/// sharing a digest with a tag that is kept are skipped, and selected tags that only share their
+/// digest with other selected tags are deleted together by a single forced deletion if
+/// `force_shared_digests` is true, or skipped otherwise.
+///
+/// Selected tags that aren't `Ready` are skipped as well, since deleting a tag that is already
+/// being deleted only fails.
+pub fn plan_deletions<'a>(
+    image_tags: &'a [ImageTag],
+    selected: &[&'a ImageTag],
+    force_shared_digests: bool,
+) -> DeletionPlan<'a> {
+    let mut digest_counts: HashMap<&str, usize> = HashMap::new();
+    let mut selected_by_digest: HashMap<&str, Vec<&'a ImageTag>> = HashMap::new();
+
+    for tag in image_tags {
+        *digest_counts.entry(tag.digest()).or_insert(0) += 1;
+    }
+
+    let mut plan = DeletionPlan::default();
+    let mut ready = Vec::with_capacity(selected.len());
+
+    for &tag in selected {
+        match tag.status() {
+            Status::Ready => ready.push(tag),
+            status => plan.skipped.push((tag, SkipReason::NotReady(status))),
+        }
+    }
+
+    for &tag in ready.iter() {
+        selected_by_digest
+            .entry(tag.digest())
+            .or_default()
+            .push(tag);
+    }
+
+    for &tag in ready.iter() {
+        let group = &selected_by_digest[tag.digest()];
+
+        if group.len() < digest_counts[tag.digest()] {
+            plan.skipped.push((tag, SkipReason::SharedWithKeptTag));
+        } else if group.len() == 1 {
+            plan.deletions.push(Deletion {
+                tag,
+                shared: vec![],
+            });
+        } else if !force_shared_digests {
+            plan.skipped.push((tag, SkipReason::SharedDigest));
+        } else if group[0].id() == tag.id() {
+            plan.deletions.push(Deletion {
+                tag,
+                shared: group[1..].to_vec(),
+            });
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resume::ResumeLog;
+    use crate::test_helpers::{image_tag_with_digest, image_with_id};
+
+    use chrono::{Duration, Utc};
+
+    /// Returns a list of tags with the given names and digests, newest first
+    fn tags(names_and_digests: &[(&str, &str)]) -> Vec<ImageTag> {
+        let now = Utc::now();
+
+        names_and_digests
+            .iter()
+            .enumerate()
+            .map(|(i, (name, digest))| {
+                image_tag_with_digest(name, digest, now - Duration::days(i as i64))
+            })
+            .collect()
+    }
+
+    /// Returns the names of the given `tags`
+    fn tag_names<'a>(tags: &[&'a ImageTag]) -> Vec<&'a str> {
+        tags.iter().map(|t| t.name()).collect()
+    }
+
+    #[test]
+    fn it_deletes_tags_with_unique_digests_without_force() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("0.9", "sha256:c"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+
+        assert_eq!(tag_names(&plan.tags()), ["1.0", "0.9"]);
+        assert!(plan.deletions.iter().all(|d| !d.force()));
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn it_skips_tags_that_arent_ready() {
+        let list: serde_json::Value = serde_json::from_str(include_str!(
+            "../../scaleway_sdk/tests/fixtures/image_tag_list_mixed_status.json"
+        ))
+        .unwrap();
+        let tags = list["tags"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tag| serde_json::from_str::<ImageTag>(&tag.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+
+        assert_eq!(tag_names(&plan.tags()), ["b6f0b34"]);
+        assert_eq!(
+            plan.skipped
+                .iter()
+                .map(|(t, r)| (t.name(), r))
+                .collect::<Vec<_>>(),
+            [
+                ("168fbd0", &SkipReason::NotReady(Status::Deleting)),
+                ("1b91e8c", &SkipReason::NotReady(Status::Error))
+            ]
+        );
+    }
+
+    #[test]
+    fn it_skips_tags_deleted_by_a_previous_run() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("0.9", "sha256:c"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let mut plan = plan_deletions(&tags, &selected, false);
+
+        plan.skip_deleted(|tag| tag.name() == "1.0");
+
+        assert_eq!(tag_names(&plan.tags()), ["0.9"]);
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].0.name(), "1.0");
+        assert_eq!(plan.skipped[0].1, SkipReason::AlreadyDeleted);
+    }
+
+    #[test]
+    fn it_skips_the_shared_tags_of_a_digest_deleted_by_a_previous_run() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("1.0-slim", "sha256:b"),
+            ("0.9", "sha256:c"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let mut plan = plan_deletions(&tags, &selected, true);
+        let path = std::env::temp_dir().join(format!("scw-prune-plan-{}.log", std::process::id()));
+
+        // A forced deletion logs every tag sharing the digest
+        std::fs::write(&path, "1.0-id\n1.0-slim-id\n").unwrap();
+
+        let resume_log = ResumeLog::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        plan.skip_deleted(|tag| resume_log.contains(tag.id()));
+
+        assert_eq!(tag_names(&plan.tags()), ["0.9"]);
+        assert_eq!(
+            plan.skipped
+                .iter()
+                .map(|(t, r)| (t.name(), r))
+                .collect::<Vec<_>>(),
+            [
+                ("1.0", &SkipReason::AlreadyDeleted),
+                ("1.0-slim", &SkipReason::AlreadyDeleted)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_skips_digests_in_use_by_other_images() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("1.0-slim", "sha256:b"),
+            ("0.9", "sha256:c"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let mut plan = plan_deletions(&tags, &selected, true);
+
+        plan.skip_digests_in_use(|digest| {
+            Some("worker".to_owned()).filter(|_| digest == "sha256:b")
+        });
+
+        assert_eq!(tag_names(&plan.tags()), ["0.9"]);
+        assert_eq!(
+            plan.skipped
+                .iter()
+                .map(|(t, r)| (t.name(), r))
+                .collect::<Vec<_>>(),
+            [
+                ("1.0", &SkipReason::DigestInUse("worker".to_owned())),
+                ("1.0-slim", &SkipReason::DigestInUse("worker".to_owned()))
+            ]
+        );
+    }
+
+    #[test]
+    fn it_indexes_the_images_of_each_digest() {
+        let app = image_with_id("app-id", "app");
+        let worker = image_with_id("worker-id", "worker");
+        let app_tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("1.1", "sha256:b"),
+        ]);
+        let worker_tags = tags(&[("latest", "sha256:b")]);
+
+        let index = index_digests(vec![(&app, &app_tags[..]), (&worker, &worker_tags[..])]);
+        let names = |digest| index[digest].iter().map(|i| i.name()).collect::<Vec<_>>();
+
+        assert_eq!(names("sha256:a"), ["app"]);
+        assert_eq!(names("sha256:b"), ["app", "worker"]);
+    }
+
+    #[test]
+    fn it_skips_tags_sharing_digest_with_kept_tag() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.1", "sha256:a"),
+            ("1.0", "sha256:b"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, true);
+
+        assert_eq!(tag_names(&plan.tags()), ["1.0"]);
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].0.name(), "1.1");
+        assert_eq!(plan.skipped[0].1, SkipReason::SharedWithKeptTag);
+    }
+
+    #[test]
+    fn it_skips_fully_selected_shared_digests_without_force() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("stable", "sha256:b"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+
+        assert!(plan.is_empty());
+        assert_eq!(
+            plan.skipped.iter().map(|(_, r)| r).collect::<Vec<_>>(),
+            [&SkipReason::SharedDigest, &SkipReason::SharedDigest]
+        );
+    }
+
+    #[test]
+    fn it_forces_a_single_deletion_for_fully_selected_shared_digests() {
+        let tags = tags(&[
+            ("latest", "sha256:a"),
+            ("1.0", "sha256:b"),
+            ("0.9", "sha256:c"),
+            ("stable", "sha256:b"),
+        ]);
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, true);
+
+        assert_eq!(plan.deletions.len(), 2);
+        assert_eq!(plan.deletions[0].tag.name(), "1.0");
+        assert!(plan.deletions[0].force());
+        assert_eq!(tag_names(&plan.deletions[0].shared), ["stable"]);
+        assert_eq!(plan.deletions[1].tag.name(), "0.9");
+        assert!(!plan.deletions[1].force());
+        assert_eq!(tag_names(&plan.tags()), ["1.0", "stable", "0.9"]);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use scaleway_sdk::registry::{Image, ImageTag};
+
+use crate::table::format_date_time;
+
+/// A tag in a saved deletion plan
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PlannedTag {
+    pub id: String,
+    pub name: String,
+    pub digest: String,
+}
+
+/// An image in a saved deletion plan, along with the tags to delete from it
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PlannedImage {
+    pub id: String,
+    pub name: String,
+    pub tags: Vec<PlannedTag>,
+}
+
+/// A deletion plan written to a file with `--plan-file`, to be reviewed and carried out later
+/// with `--apply-plan`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PlanFile {
+    pub created_at: DateTime<Utc>,
+    pub region: String,
+    pub images: Vec<PlannedImage>,
+}
+
+impl PlanFile {
+    /// Returns the plan file deleting the tags of each image in `plans` from `region`
+    pub fn new(
+        region: &str,
+        plans: &[(&Image, Vec<&ImageTag>)],
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        let images = plans
+            .iter()
+            .map(|(image, tags)| PlannedImage {
+                id: image.id().to_owned(),
+                name: image.name().to_owned(),
+                tags: tags
+                    .iter()
+                    .map(|t| PlannedTag {
+                        id: t.id().to_owned(),
+                        name: t.name().to_owned(),
+                        digest: t.digest().to_owned(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        PlanFile {
+            created_at,
+            region: region.to_owned(),
+            images,
+        }
+    }
+
+    /// Reads the plan file at `path`
+    pub fn load(path: &Path) -> io::Result<PlanFile> {
+        let contents = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes the plan to the file at `path`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+
+        fs::write(path, contents)
+    }
+
+    /// Returns the tags that are in this plan but not in the `previous` plan, and the other way
+    /// around, comparing them by ID
+    pub fn diff<'a>(&'a self, previous: &'a PlanFile) -> PlanDiff<'a> {
+        PlanDiff {
+            since: previous.created_at,
+            added: tags_missing_from(self, previous),
+            removed: tags_missing_from(previous, self),
+        }
+    }
+
+    /// Returns every tag of the plan along with the name of its image
+    fn tags(&self) -> impl Iterator<Item = PlannedTagRef<'_>> {
+        self.images
+            .iter()
+            .flat_map(|image| image.tags.iter().map(move |tag| (image.name.as_str(), tag)))
+    }
+}
+
+/// A tag in a saved deletion plan, along with the name of its image
+pub type PlannedTagRef<'a> = (&'a str, &'a PlannedTag);
+
+/// The tags that entered and left the deletion plan since an earlier plan
+#[derive(Debug, PartialEq)]
+pub struct PlanDiff<'a> {
+    /// When the earlier plan was made
+    pub since: DateTime<Utc>,
+    /// The tags that are newly planned to be deleted
+    pub added: Vec<PlannedTagRef<'a>>,
+    /// The tags that were planned to be deleted before, but no longer are
+    pub removed: Vec<PlannedTagRef<'a>>,
+}
+
+/// Returns the tags of `plan` that aren't in `other`
+fn tags_missing_from<'a>(plan: &'a PlanFile, other: &PlanFile) -> Vec<PlannedTagRef<'a>> {
+    let ids = other
+        .tags()
+        .map(|(_, tag)| tag.id.as_str())
+        .collect::<HashSet<&str>>();
+
+    plan.tags()
+        .filter(|(_, tag)| !ids.contains(tag.id.as_str()))
+        .collect()
+}
+
+impl<'a> PlanDiff<'a> {
+    /// Returns the added tags prefixed with `+` and the removed tags prefixed with `-`
+    pub fn format(&self) -> String {
+        let since = format_date_time(self.since);
+
+        if self.added.is_empty() && self.removed.is_empty() {
+            return format!("No changes since the plan of {}\n", since);
+        }
+
+        let mut out = format!("Changes since the plan of {}:\n", since);
+        let added = self.added.iter().map(|tag| ('+', tag));
+        let removed = self.removed.iter().map(|tag| ('-', tag));
+
+        for (sign, (image, tag)) in added.chain(removed) {
+            out.push_str(&format!("  {} {}:{}\n", sign, image, tag.name));
+        }
+
+        out
+    }
+}
+
+impl PlannedImage {
+    /// Returns the current `tags` of the image that the plan deletes
+    ///
+    /// Fails with the first planned tag that no longer exists or has been pushed again with a
+    /// different digest since the plan was written, as deleting it would no longer delete what was
+    /// reviewed.
+    pub fn resolve<'a>(&self, tags: &'a [ImageTag]) -> Result<Vec<&'a ImageTag>, &PlannedTag> {
+        self.tags
+            .iter()
+            .map(|planned| {
+                tags.iter()
+                    .find(|t| t.id() == planned.id && t.digest() == planned.digest)
+                    .ok_or(planned)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{image, image_tag, image_tag_with_digest};
+
+    fn plan_file(tags: &[ImageTag]) -> PlanFile {
+        let image = image("app");
+
+        PlanFile::new("nl-ams", &[(&image, tags.iter().collect())], Utc::now())
+    }
+
+    #[test]
+    fn it_round_trips_through_a_file() {
+        let tags = vec![image_tag("a", Utc::now()), image_tag("b", Utc::now())];
+        let plan = plan_file(&tags);
+        let path = std::env::temp_dir().join(format!("scw-prune-plan-{}.json", std::process::id()));
+
+        plan.save(&path).unwrap();
+
+        let loaded = PlanFile::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, plan);
+        assert_eq!(loaded.images[0].name, "app");
+        assert_eq!(loaded.images[0].tags[1].id, "b-id");
+    }
+
+    #[test]
+    fn it_rejects_malformed_plan_files() {
+        let path =
+            std::env::temp_dir().join(format!("scw-prune-bad-plan-{}.json", std::process::id()));
+
+        fs::write(&path, "not json").unwrap();
+
+        let err = PlanFile::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_diffs_two_plans_by_tag_id() {
+        let now = Utc::now();
+        let tags = [
+            image_tag("a", now),
+            image_tag("b", now),
+            image_tag("c", now),
+        ];
+        let previous = plan_file(&tags[..2]);
+        let current = plan_file(&tags[1..]);
+
+        let diff = current.diff(&previous);
+        let names = |tags: &[PlannedTagRef]| {
+            tags.iter()
+                .map(|(image, tag)| format!("{}:{}", image, tag.name))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&diff.added), ["app:c"]);
+        assert_eq!(names(&diff.removed), ["app:a"]);
+        assert_eq!(diff.since, previous.created_at);
+    }
+
+    #[test]
+    fn it_formats_plan_diffs() {
+        let now = Utc::now();
+        let tags = [image_tag("a", now), image_tag("b", now)];
+        let mut previous = plan_file(&tags[..1]);
+        previous.created_at = "2020-04-14T21:23:48Z".parse().unwrap();
+        let current = plan_file(&tags[1..]);
+
+        assert_eq!(
+            current.diff(&previous).format(),
+            "Changes since the plan of 2020-04-14 21:23:48:\n  + app:b\n  - app:a\n"
+        );
+        assert_eq!(
+            previous.diff(&previous).format(),
+            "No changes since the plan of 2020-04-14 21:23:48\n"
+        );
+    }
+
+    #[test]
+    fn it_resolves_the_planned_tags() {
+        let now = Utc::now();
+        let tags = vec![
+            image_tag("a", now),
+            image_tag("b", now),
+            image_tag("c", now),
+        ];
+        let plan = plan_file(&tags[..2]);
+
+        let resolved = plan.images[0].resolve(&tags).unwrap();
+
+        assert_eq!(
+            resolved.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn it_rejects_tags_that_no_longer_exist() {
+        let now = Utc::now();
+        let tags = vec![image_tag("a", now), image_tag("b", now)];
+        let plan = plan_file(&tags);
+
+        let err = plan.images[0].resolve(&tags[..1]).unwrap_err();
+
+        assert_eq!(err.name, "b");
+    }
+
+    #[test]
+    fn it_rejects_tags_pushed_again_with_another_digest() {
+        let now = Utc::now();
+        let plan = plan_file(&[image_tag("a", now)]);
+        let tags = vec![image_tag_with_digest("a", "sha256:new", now)];
+
+        let err = plan.images[0].resolve(&tags).unwrap_err();
+
+        assert_eq!(err.name, "a");
+    }
+}
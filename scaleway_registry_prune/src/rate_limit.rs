@@ -0,0 +1,57 @@
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::{self, Instant};
+
+/// Spaces out requests so that no more than a given number of them are started per second,
+/// regardless of how many are in flight at once
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Returns a rate limiter that lets `n` requests start per second
+    pub fn per_second(n: NonZeroU32) -> RateLimiter {
+        RateLimiter {
+            interval: Duration::from_secs(1) / n.get(),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until the next request is allowed to start
+    ///
+    /// Each caller reserves the next free slot before waiting, so concurrent callers are let
+    /// through one `interval` apart in the order they called.
+    pub async fn acquire(&self) {
+        let at = {
+            let mut next = self.next.lock().unwrap();
+            let at = (*next).max(Instant::now());
+
+            *next = at + self.interval;
+            at
+        };
+
+        time::sleep_until(at).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future::join_all;
+
+    #[tokio::test]
+    async fn it_spaces_out_concurrent_requests() {
+        let limiter = RateLimiter::per_second(NonZeroU32::new(20).unwrap());
+        let start = Instant::now();
+
+        join_all((0..3).map(|_| limiter.acquire())).await;
+
+        // The first request starts right away and the following two 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::time::Duration;
+
+use scaleway_sdk::registry::{Image, ImageTag};
+
+use crate::plan::{Deletion, DeletionPlan};
+
+/// A tag along with the image it belongs to
+pub type TagRef<'a> = (&'a Image, &'a ImageTag);
+
+/// The outcome of carrying out the deletion plans of a prune run
+#[derive(Debug)]
+pub struct PruneReport<'a> {
+    /// The planned tags that were deleted
+    pub deleted: Vec<TagRef<'a>>,
+    /// The tags that were selected for deletion but left alone by the plan
+    pub skipped: Vec<TagRef<'a>>,
+    /// The planned tags that were left alone because they were pushed again after being listed
+    pub changed: Vec<TagRef<'a>>,
+    /// The planned tags that weren't deleted because the run was interrupted first
+    pub interrupted: Vec<TagRef<'a>>,
+    /// The planned tags that couldn't be deleted, along with the error
+    pub failed: Vec<(TagRef<'a>, String)>,
+    /// How long the deletions took
+    pub elapsed: Duration,
+}
+
+impl<'a> PruneReport<'a> {
+    /// Returns the report of carrying out the `plans`, where the `failed` deletions returned an
+    /// error, the `changed` deletions were refused because their tag changed and the
+    /// `interrupted` deletions were never started
+    ///
+    /// A failed forced deletion fails every tag sharing its digest, so they're all reported as
+    /// failed.
+    pub fn new<E: Display>(
+        plans: &[(&'a Image, &DeletionPlan<'a>)],
+        failed: &[((&'a Image, &Deletion<'a>), E)],
+        changed: &[(&'a Image, &Deletion<'a>)],
+        interrupted: &[(&'a Image, &Deletion<'a>)],
+        elapsed: Duration,
+    ) -> Self {
+        let failed = failed
+            .iter()
+            .flat_map(|((image, deletion), err)| {
+                std::iter::once(deletion.tag)
+                    .chain(deletion.shared.iter().copied())
+                    .map(move |tag| ((*image, tag), err.to_string()))
+            })
+            .collect::<Vec<_>>();
+        let changed = deleted_tags(changed);
+        let interrupted = deleted_tags(interrupted);
+        let failed_ids = failed
+            .iter()
+            .map(|((_, tag), _)| tag.id())
+            .chain(changed.iter().map(|(_, tag)| tag.id()))
+            .chain(interrupted.iter().map(|(_, tag)| tag.id()))
+            .collect::<HashSet<&str>>();
+
+        let planned = plans
+            .iter()
+            .flat_map(|(image, plan)| plan.tags().into_iter().map(move |tag| (*image, tag)))
+            .collect::<Vec<_>>();
+        let deleted = planned
+            .iter()
+            .copied()
+            .filter(|(_, tag)| !failed_ids.contains(tag.id()))
+            .collect();
+        let skipped = plans
+            .iter()
+            .flat_map(|(image, plan)| plan.skipped.iter().map(move |(tag, _)| (*image, *tag)))
+            .collect();
+
+        PruneReport {
+            deleted,
+            skipped,
+            changed,
+            interrupted,
+            failed,
+            elapsed,
+        }
+    }
+
+    /// Moves the `pending` tags from the deleted tags to the failed ones, failing with `err`
+    pub fn fail_deleted(&mut self, pending: &[TagRef<'a>], err: &str) {
+        let ids = pending
+            .iter()
+            .map(|(_, tag)| tag.id())
+            .collect::<HashSet<&str>>();
+
+        self.deleted.retain(|(_, tag)| !ids.contains(tag.id()));
+        self.failed
+            .extend(pending.iter().map(|tag| (*tag, err.to_owned())));
+    }
+
+    /// Returns the tags of `image` that were deleted
+    pub fn deleted_tags_of(&self, image: &Image) -> Vec<&'a ImageTag> {
+        self.deleted
+            .iter()
+            .filter(|(i, _)| i.id() == image.id())
+            .map(|(_, tag)| *tag)
+            .collect()
+    }
+}
+
+/// Returns every tag that the `deletions` delete, along with its image
+fn deleted_tags<'a>(deletions: &[(&'a Image, &Deletion<'a>)]) -> Vec<TagRef<'a>> {
+    deletions
+        .iter()
+        .flat_map(|(image, deletion)| {
+            std::iter::once(deletion.tag)
+                .chain(deletion.shared.iter().copied())
+                .map(move |tag| (*image, tag))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::plan_deletions;
+
+    use crate::test_helpers::{image, image_tag, image_tag_with_digest};
+    use chrono::Utc;
+
+    /// Returns the `image:tag` names of the given `tags`
+    fn names(tags: &[TagRef]) -> Vec<String> {
+        tags.iter()
+            .map(|(image, tag)| format!("{}:{}", image.name(), tag.name()))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_a_fully_successful_run() {
+        let now = Utc::now();
+        let image = image("app");
+        let tags = vec![image_tag("latest", now), image_tag("1.0", now)];
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+        let report =
+            PruneReport::new::<&str>(&[(&image, &plan)], &[], &[], &[], Duration::from_secs(2));
+
+        assert_eq!(names(&report.deleted), ["app:1.0"]);
+        assert!(report.skipped.is_empty());
+        assert!(report.changed.is_empty());
+        assert!(report.interrupted.is_empty());
+        assert!(report.failed.is_empty());
+        assert_eq!(report.elapsed, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn it_reports_skipped_and_failed_tags() {
+        let now = Utc::now();
+        let image = image("app");
+        let tags = vec![
+            image_tag_with_digest("latest", "sha256:a", now),
+            image_tag_with_digest("1.1", "sha256:a", now),
+            image_tag_with_digest("1.0", "sha256:b", now),
+            image_tag_with_digest("0.9", "sha256:c", now),
+            image_tag_with_digest("stable", "sha256:c", now),
+        ];
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, true);
+        let failed = [((&image, &plan.deletions[1]), "boom")];
+        let report = PruneReport::new(&[(&image, &plan)], &failed, &[], &[], Duration::ZERO);
+
+        assert_eq!(names(&report.deleted), ["app:1.0"]);
+        assert_eq!(names(&report.skipped), ["app:1.1"]);
+        assert_eq!(
+            report
+                .failed
+                .iter()
+                .map(|((_, tag), err)| (tag.name(), err.as_str()))
+                .collect::<Vec<_>>(),
+            [("0.9", "boom"), ("stable", "boom")]
+        );
+        assert_eq!(
+            report
+                .deleted_tags_of(&image)
+                .iter()
+                .map(|t| t.name())
+                .collect::<Vec<_>>(),
+            ["1.0"]
+        );
+    }
+
+    #[test]
+    fn it_reports_changed_tags_apart_from_deleted_tags() {
+        let now = Utc::now();
+        let image = image("app");
+        let tags = vec![
+            image_tag("latest", now),
+            image_tag("1.1", now),
+            image_tag("1.0", now),
+        ];
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+        let changed = [(&image, &plan.deletions[0])];
+        let report =
+            PruneReport::new::<&str>(&[(&image, &plan)], &[], &changed, &[], Duration::ZERO);
+
+        assert_eq!(names(&report.deleted), ["app:1.0"]);
+        assert_eq!(names(&report.changed), ["app:1.1"]);
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn it_fails_deleted_tags_that_are_still_pending() {
+        let now = Utc::now();
+        let image = image("app");
+        let tags = vec![
+            image_tag("latest", now),
+            image_tag("1.1", now),
+            image_tag("1.0", now),
+        ];
+        let selected = tags[1..].iter().collect::<Vec<_>>();
+        let plan = plan_deletions(&tags, &selected, false);
+        let mut report =
+            PruneReport::new::<&str>(&[(&image, &plan)], &[], &[], &[], Duration::ZERO);
+        let pending = [(&image, &tags[2])];
+
+        report.fail_deleted(&pending, "still being deleted");
+
+        assert_eq!(names(&report.deleted), ["app:1.1"]);
+        assert_eq!(
+            report
+                .failed
+                .iter()
+                .map(|((_, tag), err)| (tag.name(), err.as_str()))
+                .collect::<Vec<_>>(),
+            [("1.0", "still being deleted")]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// An append-only log of the IDs of the tags deleted so far, one per line, so that an
+/// interrupted run can be resumed without deleting the same tags again
+#[derive(Debug)]
+pub struct ResumeLog {
+    file: File,
+    deleted: HashSet<String>,
+}
+
+impl ResumeLog {
+    /// Opens the log at `path` for appending, reading the IDs that were logged by previous runs
+    ///
+    /// A log that doesn't exist yet is created.
+    pub fn open(path: &Path) -> io::Result<ResumeLog> {
+        let deleted = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(ResumeLog { file, deleted })
+    }
+
+    /// Returns true if the tag with the given `id` was deleted by a previous run
+    pub fn contains(&self, id: &str) -> bool {
+        self.deleted.contains(id)
+    }
+
+    /// Appends the `id` of a deleted tag to the log
+    ///
+    /// Each ID is written with a single write, so concurrent deletions don't interleave lines.
+    pub fn record(&self, id: &str) -> io::Result<()> {
+        (&self.file).write_all(format!("{}\n", id).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_back_recorded_ids() {
+        let path =
+            std::env::temp_dir().join(format!("scw-prune-resume-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let log = ResumeLog::open(&path).unwrap();
+
+        assert!(!log.contains("a"));
+
+        log.record("a").unwrap();
+        log.record("b").unwrap();
+        drop(log);
+
+        let log = ResumeLog::open(&path).unwrap();
+
+        assert!(log.contains("a"));
+        assert!(log.contains("b"));
+        assert!(!log.contains("c"));
+
+        log.record("c").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+
+use scaleway_sdk::registry::{Image, ImageTag};
+
+use crate::format_age;
+use crate::table::format_date_time;
+
+/// Aggregate numbers about the tags of a set of images
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub images: usize,
+    pub tags: usize,
+    pub digests: usize,
+    /// The time the least recently updated tag was updated
+    pub oldest: Option<DateTime<Utc>>,
+    /// The time the most recently updated tag was updated
+    pub newest: Option<DateTime<Utc>>,
+    /// The total size of the images in bytes, as reported by the API
+    pub size: u64,
+}
+
+impl Stats {
+    /// Returns the stats of the given images and all of their tags
+    pub fn new(image_tags: &[(&Image, Vec<ImageTag>)]) -> Stats {
+        let tags = || image_tags.iter().flat_map(|(_, tags)| tags.iter());
+
+        Stats {
+            images: image_tags.len(),
+            tags: tags().count(),
+            digests: tags().map(|t| t.digest()).collect::<HashSet<_>>().len(),
+            oldest: tags().map(|t| t.updated_at()).min(),
+            newest: tags().map(|t| t.updated_at()).max(),
+            size: image_tags
+                .iter()
+                .map(|(image, _)| image.size() as u64)
+                .sum(),
+        }
+    }
+
+    /// Returns a summary block of the stats, with the age of the tags relative to `now`
+    pub fn format(&self, now: DateTime<Utc>) -> String {
+        let date_time = |date_time: Option<DateTime<Utc>>| match date_time {
+            Some(date_time) => format!(
+                "{} ({})",
+                format_date_time(date_time),
+                format_age(now - date_time)
+            ),
+            None => "-".to_owned(),
+        };
+
+        format!(
+            "Images:           {}\n\
+             Tags:             {}\n\
+             Distinct digests: {}\n\
+             Oldest tag:       {}\n\
+             Newest tag:       {}\n\
+             Storage:          {:.1} MiB (estimated)\n",
+            self.images,
+            self.tags,
+            self.digests,
+            date_time(self.oldest),
+            date_time(self.newest),
+            self.size as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{image, image_tag_with_digest};
+
+    use chrono::Duration;
+
+    #[test]
+    fn it_computes_stats_across_images() {
+        let now = Utc::now();
+        let app = image("app");
+        let worker = image("worker");
+        let image_tags = vec![
+            (
+                &app,
+                vec![
+                    image_tag_with_digest("latest", "sha256:a", now),
+                    image_tag_with_digest("1.0", "sha256:a", now - Duration::days(1)),
+                ],
+            ),
+            (
+                &worker,
+                vec![image_tag_with_digest(
+                    "0.9",
+                    "sha256:b",
+                    now - Duration::days(5),
+                )],
+            ),
+        ];
+        let stats = Stats::new(&image_tags);
+
+        assert_eq!(stats.images, 2);
+        assert_eq!(stats.tags, 3);
+        assert_eq!(stats.digests, 2);
+        assert_eq!(stats.oldest, Some(now - Duration::days(5)));
+        assert_eq!(stats.newest, Some(now));
+        assert_eq!(stats.size, 2 * 123456789);
+        assert!(stats
+            .format(now)
+            .contains("Storage:          235.5 MiB (estimated)"));
+    }
+
+    #[test]
+    fn it_formats_stats_without_tags() {
+        let stats = Stats::new(&[]);
+
+        assert!(stats.format(Utc::now()).contains("Oldest tag:       -"));
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+
+use scaleway_sdk::registry::ImageTag;
+
+/// The number of hex digits of a digest that are shown, like `docker images` does
+const SHORT_DIGEST_LEN: usize = 12;
+
+/// Returns the digest of `tag` shortened to its first `SHORT_DIGEST_LEN` hex digits
+pub fn short_digest(tag: &ImageTag) -> &str {
+    let digest = tag.digest();
+    let hex = digest.find(':').map_or(0, |i| i + 1);
+
+    digest.get(..hex + SHORT_DIGEST_LEN).unwrap_or(digest)
+}
+
+/// Returns the given `date_time` formatted for a table
+pub fn format_date_time(date_time: DateTime<Utc>) -> String {
+    date_time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Returns a table of the given `rows` below the `headers`, where each column is as wide as its
+/// widest cell
+pub fn format_table<const N: usize>(headers: [&str; N], rows: &[[String; N]]) -> String {
+    let mut widths = headers.map(str::len);
+
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let headers = headers.map(str::to_owned);
+
+    std::iter::once(&headers)
+        .chain(rows.iter())
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+
+    #[test]
+    fn it_aligns_columns_to_the_widest_cell() {
+        let rows = [
+            ["a".to_owned(), "first".to_owned()],
+            ["longer".to_owned(), "second".to_owned()],
+        ];
+
+        assert_eq!(
+            format_table(["NAME", "VALUE"], &rows),
+            "NAME    VALUE\na       first\nlonger  second\n"
+        );
+    }
+
+    #[test]
+    fn it_shortens_digests() {
+        let tag = test_helpers::image_tag_with_digest(
+            "latest",
+            "sha256:1675dda591024811b7f7e67451cab2a4d85967475234285ec8eabaebb508f45e",
+            Utc::now(),
+        );
+
+        assert_eq!(short_digest(&tag), "sha256:1675dda59102");
+    }
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+
+use scaleway_sdk::registry::{Image, ImageTag, Namespace};
+
+/// Returns an `ImageTag` with the given `name` that was last updated at `updated_at`
+pub fn image_tag(name: &str, updated_at: DateTime<Utc>) -> ImageTag {
+    image_tag_with_digest(name, &format!("sha256:{}", name), updated_at)
+}
+
+/// Returns an `ImageTag` with the given `name` that was created at `created_at` and last updated
+/// at `updated_at`
+pub fn image_tag_created_at(
+    name: &str,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+) -> ImageTag {
+    image_tag_from(name, &format!("sha256:{}", name), created_at, updated_at)
+}
+
+/// Returns an `ImageTag` with the given `name` and `digest` that was last updated at `updated_at`
+pub fn image_tag_with_digest(name: &str, digest: &str, updated_at: DateTime<Utc>) -> ImageTag {
+    image_tag_from(name, digest, updated_at, updated_at)
+}
+
+fn image_tag_from(
+    name: &str,
+    digest: &str,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+) -> ImageTag {
+    let json = serde_json::json!({
+        "id": format!("{}-id", name),
+        "name": name,
+        "image_id": "b00f6b0a-cc14-4c21-843f-3acda6ebb001",
+        "status": "ready",
+        "digest": digest,
+        "created_at": created_at,
+        "updated_at": updated_at,
+    });
+
+    serde_json::from_str(&json.to_string()).unwrap()
+}
+
+/// Returns an `Image` with the given `name`
+pub fn image(name: &str) -> Image {
+    image_with_id("b00f6b0a-cc14-4c21-843f-3acda6ebb001", name)
+}
+
+/// Returns an `Image` with the given `id` and `name`
+pub fn image_with_id(id: &str, name: &str) -> Image {
+    let json = serde_json::json!({
+        "id": id,
+        "name": name,
+        "namespace_id": "1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4",
+        "status": "ready",
+        "status_message": null,
+        "visibility": "inherit",
+        "size": 123456789,
+        "created_at": "2019-08-28T12:52:30.621274Z",
+        "updated_at": "2020-04-14T21:23:48.831047Z",
+        "tags": [],
+    });
+
+    serde_json::from_str(&json.to_string()).unwrap()
+}
+
+/// Returns a `Namespace` with the given `name`, which the images returned by `image` belong to
+pub fn namespace(name: &str) -> Namespace {
+    let json = serde_json::json!({
+        "id": "1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4",
+        "name": name,
+        "description": "",
+        "organization_id": "87c1cb90-911f-46a9-84d8-b8750c8bc5a0",
+        "status": "ready",
+        "status_message": "",
+        "endpoint": format!("rg.nl-ams.scw.cloud/{}", name),
+        "is_public": false,
+        "size": null,
+        "created_at": "2019-08-20T16:00:28.379816Z",
+        "updated_at": "2020-04-09T04:29:55.424234Z",
+        "image_count": 0,
+    });
+
+    serde_json::from_str(&json.to_string()).unwrap()
+}
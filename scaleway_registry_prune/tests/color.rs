@@ -0,0 +1,33 @@
+mod support;
+
+fn dry_run_stdout(color: &str) -> String {
+    let server = support::registry();
+    let output = support::command(server.addr())
+        .env_remove("NO_COLOR")
+        .args(["mynamespace/myimage", "--keep-last", "23", "--dry-run"])
+        .args(["--color", color])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn it_colors_the_plan_when_asked_to() {
+    assert!(dry_run_stdout("always").contains("\x1b["));
+}
+
+#[test]
+fn it_doesnt_color_the_plan_with_color_never() {
+    let stdout = dry_run_stdout("never");
+
+    assert!(stdout.contains("myimage:"));
+    assert!(!stdout.contains("\x1b["));
+}
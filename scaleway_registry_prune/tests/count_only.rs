@@ -0,0 +1,53 @@
+mod support;
+
+use std::process::Output;
+
+fn run(args: &[&str]) -> Output {
+    let server = support::registry();
+
+    support::command(server.addr())
+        .arg("mynamespace/myimage")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_prints_only_the_counts() {
+    let output = run(&["--keep-last", "5", "--count-only"]);
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Would delete 22 of 27 tags (keeping 5)\n"
+    );
+}
+
+#[test]
+fn it_counts_the_tags_of_the_dry_run() {
+    for filters in [
+        &["--keep-last", "5"][..],
+        &["--older-than", "2020-01-01"],
+        &["--keep-last", "20", "--older-than", "2020-01-01"],
+    ] {
+        let dry_run = run(&[filters, &["--dry-run", "--output", "csv"]].concat());
+        let planned = String::from_utf8_lossy(&dry_run.stdout).lines().count() - 1;
+        let output = run(&[filters, &["--count-only"]].concat());
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!(
+                "Would delete {} of 27 tags (keeping {})\n",
+                planned,
+                27 - planned
+            ),
+            "{:?}",
+            filters
+        );
+    }
+}
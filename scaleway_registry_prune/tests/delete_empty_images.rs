@@ -0,0 +1,64 @@
+mod support;
+use support::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn it_deletes_images_left_without_tags() {
+    let tags_deleted = Arc::new(AtomicBool::new(false));
+    let image_deleted = Arc::new(AtomicBool::new(false));
+    let server = server::http({
+        let tags_deleted = tags_deleted.clone();
+        let image_deleted = image_deleted.clone();
+
+        move |req| {
+            let tags_deleted = tags_deleted.clone();
+            let image_deleted = image_deleted.clone();
+
+            async move {
+                let body = match (req.method().as_str(), req.uri().path()) {
+                    ("GET", "/namespaces") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                    }
+                    ("GET", "/images") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                    }
+                    ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags") => {
+                        if tags_deleted.load(Ordering::SeqCst) {
+                            r#"{"tags":[],"total_count":0}"#
+                        } else {
+                            include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                        }
+                    }
+                    ("DELETE", path) if path.starts_with("/tags/") => {
+                        tags_deleted.store(true, Ordering::SeqCst);
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                    }
+                    ("DELETE", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001") => {
+                        image_deleted.store(true, Ordering::SeqCst);
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_deleting.json")
+                    }
+                    (method, path) => panic!("unexpected request: {} {}", method, path),
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+    let output = support::command(server.addr())
+        .args(["mynamespace/myimage", "--no-protect-latest"])
+        .args(["--force-shared-digests", "--delete-empty-images", "--yes"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("This will delete the following images that have no tags left:"));
+    assert!(image_deleted.load(Ordering::SeqCst));
+}
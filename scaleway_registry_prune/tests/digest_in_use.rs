@@ -0,0 +1,71 @@
+mod support;
+use support::*;
+
+use std::process::Output;
+
+/// Returns a fake registry API where the `worker` image has a tag sharing the digest of the
+/// `0e36c19` tag of `myimage`
+fn registry() -> server::Server {
+    server::http(move |req| async move {
+        let body = match (req.method().as_str(), req.uri().path(), req.uri().query()) {
+            ("GET", "/namespaces", _) => {
+                include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+            }
+            ("GET", "/images", Some("page=1&page_size=100")) => {
+                include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+            }
+            ("GET", "/images", Some("page=2&page_size=100")) => {
+                include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_2.json")
+            }
+            ("GET", "/images", Some("page=3&page_size=100")) => {
+                include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_3.json")
+            }
+            ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags", _) => {
+                include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+            }
+            ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb002/tags", _) => {
+                include_str!("fixtures/worker_tag_list.json")
+            }
+            ("GET", path, _) if path.ends_with("/tags") => r#"{"tags":[],"total_count":0}"#,
+            (method, path, query) => panic!("unexpected request: {} {} {:?}", method, path, query),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    })
+}
+
+fn dry_run(args: &[&str]) -> Output {
+    let server = registry();
+
+    support::command(server.addr())
+        .args(["mynamespace/myimage", "--keep-last", "5", "--dry-run"])
+        .args(["--output", "csv"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_skips_digests_in_use_by_another_image() {
+    let output = dry_run(&["--protect-tags-with-digest-in-use"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(
+        stderr.contains("Skipping myimage:0e36c19: its digest is in use by the image worker"),
+        "{}",
+        stderr
+    );
+    assert!(!stdout.contains(",0e36c19,"), "{}", stdout);
+    assert_eq!(stdout.lines().count(), 22, "{}", stdout);
+}
+
+#[test]
+fn it_ignores_other_images_by_default() {
+    let output = dry_run(&[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains(",0e36c19,"), "{}", stdout);
+    assert_eq!(stdout.lines().count(), 23, "{}", stdout);
+}
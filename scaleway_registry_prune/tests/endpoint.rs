@@ -0,0 +1,18 @@
+mod support;
+
+#[test]
+fn it_lists_image_tags_from_a_custom_endpoint() {
+    let server = support::registry();
+    let output = support::bare_command()
+        .env("SCW_API_URL", format!("http://{}", server.addr()))
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["mynamespace/myimage", "--list"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.starts_with("TAG "));
+    assert!(stdout.contains("myimage:latest "));
+    assert_eq!(stdout.lines().count(), 28);
+}
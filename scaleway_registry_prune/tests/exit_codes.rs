@@ -0,0 +1,154 @@
+mod support;
+use support::*;
+
+#[test]
+fn it_exits_with_configuration_error_code_without_region() {
+    let output = bare_command().arg("namespace/image").output().unwrap();
+
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No region given"));
+}
+
+#[test]
+fn it_exits_with_configuration_error_code_without_credentials() {
+    let output = bare_command()
+        .args(["--region", "nl-ams", "namespace/image"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(7));
+}
+
+#[test]
+fn it_exits_with_configuration_error_code_with_a_malformed_proxy() {
+    let output = bare_command()
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--proxy", "http://[::1", "namespace/image"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid proxy URL"));
+}
+
+#[test]
+fn it_ignores_the_config_file_when_region_and_credentials_are_given() {
+    let server = registry();
+    let path =
+        std::env::temp_dir().join(format!("scw-prune-bad-config-{}.yaml", std::process::id()));
+
+    std::fs::write(&path, "profiles: [not a map").unwrap();
+
+    let output = bare_command()
+        .env("SCW_CONFIG_PATH", &path)
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--profile", "missing"])
+        .args(["--endpoint", &format!("http://{}", server.addr())])
+        .args(["mynamespace/myimage", "--list"])
+        .output()
+        .unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn it_exits_with_configuration_error_code_with_an_unknown_config_region() {
+    let path = std::env::temp_dir().join(format!(
+        "scw-prune-region-config-{}.yaml",
+        std::process::id()
+    ));
+
+    std::fs::write(&path, "secret_key: secret\ndefault_region: nl-amz\n").unwrap();
+
+    let output = bare_command()
+        .env("SCW_CONFIG_PATH", &path)
+        .arg("namespace/image")
+        .output()
+        .unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean `nl-ams'"));
+}
+
+#[test]
+fn it_exits_with_authentication_error_code_when_access_is_denied() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(403)
+            .body(r#"{"message":"insufficient permissions"}"#.into())
+            .unwrap()
+    });
+    let output = bare_command()
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--endpoint", &format!("http://{}", server.addr())])
+        .arg("mynamespace/myimage")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(4), "{}", stderr);
+    assert!(stderr.contains("Access denied"));
+}
+
+#[test]
+fn it_exits_with_no_such_image_error_code_when_there_are_no_images() {
+    let server = server::http(move |req| async move {
+        let body = match req.uri().path() {
+            "/namespaces" => {
+                include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+            }
+            "/images" => r#"{"images":[],"total_count":0}"#,
+            path => panic!("unexpected request: {}", path),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+    let output = bare_command()
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--endpoint", &format!("http://{}", server.addr())])
+        .arg("mynamespace/myimage")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(3), "{}", stderr);
+    assert!(stderr.contains("No such image"));
+}
+
+#[test]
+fn it_exits_with_not_found_error_code_for_a_missing_tag() {
+    let server = registry();
+    let output = bare_command()
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--endpoint", &format!("http://{}", server.addr())])
+        .args(["mynamespace/myimage", "--tag", "b6f0b34", "--tag", "nope"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No such tag: myimage:nope"));
+}
+
+#[test]
+fn it_exits_with_not_found_error_code_for_a_missing_digest() {
+    let server = registry();
+    let output = bare_command()
+        .args(["--region", "nl-ams", "--scw-token", "token"])
+        .args(["--endpoint", &format!("http://{}", server.addr())])
+        .args(["mynamespace/myimage", "--since-digest", "sha256:missing"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("No tag of myimage has the digest sha256:missing"));
+}
@@ -0,0 +1,82 @@
+mod support;
+use support::*;
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn it_lists_the_tags_of_several_images_at_once() {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let (in_flight, peak) = (in_flight.clone(), peak.clone());
+
+        move |req| {
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+
+            async move {
+                let body = match (req.method().as_str(), req.uri().path(), req.uri().query()) {
+                    ("GET", "/namespaces", _) => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                    }
+                    ("GET", "/images", Some("page=1&page_size=100")) => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                    }
+                    ("GET", "/images", Some("page=2&page_size=100")) => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_2.json")
+                    }
+                    ("GET", "/images", Some("page=3&page_size=100")) => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_3.json")
+                    }
+                    ("GET", path, _) if path.ends_with("/tags") => {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+
+                        peak.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                    }
+                    (method, path, query) => {
+                        panic!("unexpected request: {} {} {:?}", method, path, query)
+                    }
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+
+    let output = support::command(server.addr())
+        .args(["mynamespace", "--keep-last", "25", "--dry-run"])
+        .args(["--output", "json", "--image-concurrency", "3"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let plan: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let images = plan
+        .iter()
+        .map(|deletion| deletion["image"].as_str().unwrap())
+        .collect::<HashSet<_>>();
+
+    assert_eq!(
+        images,
+        ["myimage", "worker", "frontend", "backend", "lastimage"]
+            .iter()
+            .copied()
+            .collect()
+    );
+
+    let peak = peak.load(Ordering::SeqCst);
+
+    assert!((2..=3).contains(&peak), "{} images listed at once", peak);
+}
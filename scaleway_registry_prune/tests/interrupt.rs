@@ -0,0 +1,162 @@
+#![cfg(unix)]
+
+mod support;
+use support::*;
+
+use std::fs;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn it_stops_deleting_and_exits_with_130_on_ctrl_c() {
+    let deletes = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let deletes = deletes.clone();
+
+        move |req| {
+            let deletes = deletes.clone();
+
+            async move {
+                let body = match (req.method().as_str(), req.uri().path()) {
+                    ("GET", "/namespaces") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                    }
+                    ("GET", "/images") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                    }
+                    ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                    }
+                    ("DELETE", _) => {
+                        deletes.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                    }
+                    (method, path) => panic!("unexpected request: {} {}", method, path),
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+
+    let metrics_path =
+        std::env::temp_dir().join(format!("scw-prune-interrupt-{}.prom", std::process::id()));
+    let child = spawn(
+        &server,
+        &[
+            "--keep-last",
+            "5",
+            "--metrics-file",
+            metrics_path.to_str().unwrap(),
+        ],
+    );
+
+    // Interrupt the run once the first, slow deletion is in flight
+    wait_until(|| deletes.load(Ordering::SeqCst) > 0);
+    interrupt(&child);
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(130), "{}", stderr);
+    assert!(stderr.contains("Deleted 1 tags") || stderr.contains("across 1 tags"));
+    assert!(stderr.contains("Interrupted before"), "{}", stderr);
+    assert_eq!(deletes.load(Ordering::SeqCst), 1);
+
+    let metrics = fs::read_to_string(&metrics_path).unwrap();
+    fs::remove_file(&metrics_path).unwrap();
+
+    assert!(
+        metrics.contains("\nscaleway_prune_deleted_total 1\n"),
+        "{}",
+        metrics
+    );
+}
+
+#[test]
+fn it_exits_on_the_first_ctrl_c_once_the_deletions_are_done() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let lookups = lookups.clone();
+
+        move |req| {
+            let lookups = lookups.clone();
+
+            async move {
+                let body = match (req.method().as_str(), req.uri().path()) {
+                    ("GET", "/namespaces") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                    }
+                    ("GET", "/images") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                    }
+                    ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                    }
+                    // The deleted tag never goes away, so the run keeps waiting for it
+                    ("GET", _) => {
+                        lookups.fetch_add(1, Ordering::SeqCst);
+
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                    }
+                    ("DELETE", _) => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                    }
+                    (method, path) => panic!("unexpected request: {} {}", method, path),
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+
+    let child = spawn(&server, &["--tag", "5dbc258", "--wait"]);
+
+    wait_until(|| lookups.load(Ordering::SeqCst) > 0);
+    interrupt(&child);
+
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(130),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Runs the binary against `server` with the given extra `args`, deleting without confirmation
+fn spawn(server: &server::Server, args: &[&str]) -> Child {
+    support::command(server.addr())
+        .args(["mynamespace/myimage", "--yes", "--concurrency", "1"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// Waits up to 10 seconds for `condition` to hold
+fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for the run");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Sends SIGINT to `child`, as pressing Ctrl-C would
+fn interrupt(child: &Child) {
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+}
@@ -0,0 +1,37 @@
+mod support;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn it_spaces_out_delete_requests() {
+    let deletes = Arc::new(Mutex::new(Vec::new()));
+    let server = support::registry_recording_deletes(deletes.clone());
+    let output = support::command(server.addr())
+        .args(["mynamespace/myimage", "--keep-last", "23", "--yes"])
+        .args(["--concurrency", "4", "--max-rps", "5"])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let deletes = deletes.lock().unwrap();
+
+    assert!(deletes.len() >= 3, "only {} deletes", deletes.len());
+
+    // 5 requests per second are started 200ms apart, give or take scheduling delays
+    for pair in deletes.windows(2) {
+        let gap = pair[1] - pair[0];
+
+        assert!(
+            gap >= Duration::from_millis(150),
+            "requests {:?} apart",
+            gap
+        );
+    }
+}
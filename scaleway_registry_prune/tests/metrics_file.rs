@@ -0,0 +1,95 @@
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+/// Returns the path of a metrics file holding the metrics of a previous run that deleted tags
+fn stale_metrics_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "scw-prune-metrics-{}-{}.prom",
+        name,
+        std::process::id()
+    ));
+
+    fs::write(&path, "scaleway_prune_deleted_total 22\n").unwrap();
+
+    path
+}
+
+/// Runs the binary with `args` against the fake registry, writing its metrics to `path`
+fn prune(path: &Path, args: &[&str]) -> Output {
+    let server = support::registry();
+
+    support::command(server.addr())
+        .args(["--metrics-file", path.to_str().unwrap()])
+        .arg("mynamespace/myimage")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_writes_zeros_on_a_dry_run() {
+    let path = stale_metrics_file("dry-run");
+    let output = prune(&path, &["--keep-last", "5", "--dry-run"]);
+    let metrics = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        metrics.contains("\nscaleway_prune_deleted_total 0\n"),
+        "{}",
+        metrics
+    );
+}
+
+#[test]
+fn it_writes_zeros_when_nothing_matches() {
+    let path = stale_metrics_file("no-match");
+    let output = prune(&path, &["--keep-last", "100", "--yes"]);
+    let metrics = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_ne!(output.status.code(), Some(0));
+    assert!(
+        metrics.contains("\nscaleway_prune_deleted_total 0\n"),
+        "{}",
+        metrics
+    );
+    assert!(
+        metrics.contains("\nscaleway_prune_failed_total 0\n"),
+        "{}",
+        metrics
+    );
+}
+
+#[test]
+fn it_writes_the_deletions_of_a_run() {
+    let path = stale_metrics_file("deleted");
+    let output = prune(&path, &["--keep-last", "20", "--yes"]);
+    let metrics = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        metrics.contains("\nscaleway_prune_deleted_total 7\n"),
+        "{}",
+        metrics
+    );
+    assert!(
+        metrics.contains("# TYPE scaleway_prune_deleted_total gauge\n"),
+        "{}",
+        metrics
+    );
+}
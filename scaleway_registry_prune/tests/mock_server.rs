@@ -0,0 +1,14 @@
+mod support;
+
+#[test]
+fn it_completes_the_mock_flow() {
+    let server = support::registry();
+    let output = support::command(server.addr())
+        .args(["mynamespace/myimage", "--keep-last", "5", "--yes"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("across 22 tags"), "{}", stderr);
+}
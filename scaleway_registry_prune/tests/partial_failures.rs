@@ -0,0 +1,60 @@
+mod support;
+use support::*;
+
+use std::sync::{Arc, Mutex};
+
+/// The ID of the `168fbd0` tag in `image_tag_list.json`
+const FAILING_TAG_ID: &str = "57f48dc6-c553-4163-9c43-f2f68415a50a";
+
+#[test]
+fn it_keeps_deleting_and_reports_failed_deletions() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let server = server::http({
+        let deleted = deleted.clone();
+
+        move |req| {
+            let deleted = deleted.clone();
+
+            async move {
+                let body = match (req.method().as_str(), req.uri().path()) {
+                    ("GET", "/namespaces") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                    }
+                    ("GET", "/images") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                    }
+                    ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags") => {
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                    }
+                    ("DELETE", path) if path == format!("/tags/{}", FAILING_TAG_ID) => {
+                        return http::Response::builder()
+                            .status(500)
+                            .body(r#"{"message":"internal error"}"#.into())
+                            .unwrap();
+                    }
+                    ("DELETE", path) if path.starts_with("/tags/") => {
+                        deleted.lock().unwrap().push(path.to_owned());
+                        include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                    }
+                    (method, path) => panic!("unexpected request: {} {}", method, path),
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+    let output = support::command(server.addr())
+        .args(["mynamespace/myimage", "--keep-last", "23", "--yes"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(6), "{}", stderr);
+    assert!(stderr.contains("Failed to delete myimage:168fbd0: API error (500 Internal Server Error): internal error"), "{}", stderr);
+    assert!(
+        stderr.contains("Failed to delete 1 image tags"),
+        "{}",
+        stderr
+    );
+    assert_eq!(deleted.lock().unwrap().len(), 3);
+}
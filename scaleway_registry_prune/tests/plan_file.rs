@@ -0,0 +1,75 @@
+mod support;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+use std::sync::{Arc, Mutex};
+
+fn run(server: &support::server::Server, args: &[&str]) -> Output {
+    support::command(server.addr()).args(args).output().unwrap()
+}
+
+fn write_plan(path: &Path) -> serde_json::Value {
+    let server = support::registry();
+    let output = run(
+        &server,
+        &[
+            "mynamespace/myimage",
+            "--keep-last",
+            "23",
+            "--plan-file",
+            path.to_str().unwrap(),
+        ],
+    );
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn it_applies_a_saved_plan() {
+    let path = std::env::temp_dir().join(format!("scw-prune-apply-{}.json", std::process::id()));
+    let plan = write_plan(&path);
+    let planned = plan["images"][0]["tags"].as_array().unwrap().len();
+
+    assert!(planned > 0);
+    assert_eq!(plan["images"][0]["name"], "myimage");
+
+    let deletes = Arc::new(Mutex::new(Vec::new()));
+    let server = support::registry_recording_deletes(deletes.clone());
+    let output = run(&server, &["--apply-plan", path.to_str().unwrap(), "--yes"]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(deletes.lock().unwrap().len(), planned);
+}
+
+#[test]
+fn it_refuses_to_apply_a_stale_plan() {
+    let path = std::env::temp_dir().join(format!("scw-prune-stale-{}.json", std::process::id()));
+    let mut plan = write_plan(&path);
+
+    // The tag was deleted and pushed again since the plan was written
+    plan["images"][0]["tags"][0]["id"] = "00000000-0000-0000-0000-000000000000".into();
+    fs::write(&path, plan.to_string()).unwrap();
+
+    let deletes = Arc::new(Mutex::new(Vec::new()));
+    let server = support::registry_recording_deletes(deletes.clone());
+    let output = run(&server, &["--apply-plan", path.to_str().unwrap(), "--yes"]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("The plan is out of date"));
+    assert!(deletes.lock().unwrap().is_empty());
+}
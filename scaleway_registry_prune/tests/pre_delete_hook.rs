@@ -0,0 +1,68 @@
+#![cfg(unix)]
+
+mod support;
+
+use std::fs;
+use std::process::Output;
+use std::sync::{Arc, Mutex};
+
+fn prune(hook: &str, args: &[&str], deletes: Arc<Mutex<Vec<std::time::Instant>>>) -> Output {
+    let server = support::registry_recording_deletes(deletes);
+
+    support::command(server.addr())
+        .args(["mynamespace/myimage", "--keep-last", "5", "--yes"])
+        .args(["--pre-delete-hook", hook])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_skips_the_deletions_when_the_hook_fails() {
+    let deletes = Arc::default();
+    let output = prune("cat > /dev/null; exit 3", &[], Arc::clone(&deletes));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("The pre-delete hook failed"), "{}", stderr);
+    assert!(deletes.lock().unwrap().is_empty());
+}
+
+#[test]
+fn it_passes_the_plan_to_the_hook() {
+    let path = std::env::temp_dir().join(format!("scw-prune-hook-{}.json", std::process::id()));
+    let deletes = Arc::default();
+    let output = prune(
+        &format!("cat > {}", path.display()),
+        &[],
+        Arc::clone(&deletes),
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+
+    let plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(plan["region"], "nl-ams");
+    assert_eq!(plan["images"][0]["name"], "myimage");
+    assert_eq!(plan["images"][0]["tags"].as_array().unwrap().len(), 22);
+    assert_eq!(deletes.lock().unwrap().len(), 22);
+}
+
+#[test]
+fn it_keeps_the_output_of_the_hook_out_of_stdout() {
+    let deletes = Arc::default();
+    let output = prune(
+        "cat > /dev/null; echo from-the-hook",
+        &["--output", "json"],
+        Arc::clone(&deletes),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(!stdout.contains("from-the-hook"), "{}", stdout);
+    assert!(stderr.contains("from-the-hook"), "{}", stderr);
+}
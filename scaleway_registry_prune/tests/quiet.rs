@@ -0,0 +1,36 @@
+mod support;
+
+#[test]
+fn it_prints_nothing_in_quiet_mode() {
+    let server = support::registry();
+    let output = support::command(server.addr())
+        .args([
+            "mynamespace/myimage",
+            "--keep-last",
+            "20",
+            "--quiet",
+            "--yes",
+        ])
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn it_requires_yes_in_quiet_mode() {
+    let output = support::command(([127, 0, 0, 1], 1).into())
+        .args(["mynamespace/myimage", "--keep-last", "20", "--quiet"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+}
@@ -0,0 +1,21 @@
+mod support;
+
+#[test]
+fn it_prints_stats_of_an_image() {
+    let server = support::registry();
+    let output = support::command(server.addr())
+        .args(["mynamespace/myimage", "--stats"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("Images:           1\n"), "{}", stdout);
+    assert!(stdout.contains("Tags:             27\n"), "{}", stdout);
+    assert!(stdout.contains("Distinct digests: 26\n"), "{}", stdout);
+}
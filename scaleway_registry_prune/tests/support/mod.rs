@@ -0,0 +1,98 @@
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+#[cfg(test)]
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub mod server;
+
+/// Returns the arguments connecting to the fake registry API at `addr`
+pub fn mock_args(addr: SocketAddr) -> Vec<String> {
+    [
+        "--endpoint",
+        &format!("http://{}", addr),
+        "--region",
+        "nl-ams",
+        "--scw-token",
+        "mock",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+/// Returns a command running the binary without any of the Scaleway settings of the environment
+/// and with a config file that doesn't exist
+///
+/// Only the tests know the path of the binary, so this isn't available to the mock_server example.
+#[cfg(test)]
+pub fn bare_command() -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_scaleway-registry-prune"));
+
+    cmd.env_remove("RUST_LOG")
+        .env_remove("SCW_API_URL")
+        .env_remove("SCW_PROFILE")
+        .env_remove("SCW_REGION")
+        .env_remove("SCW_SECRET_KEY")
+        .env_remove("SCW_TOKEN")
+        .env("SCW_CONFIG_PATH", "/nonexistent/scw/config.yaml");
+
+    cmd
+}
+
+/// Returns a command running the binary against the fake registry API at `addr`
+#[cfg(test)]
+pub fn command(addr: SocketAddr) -> Command {
+    let mut cmd = bare_command();
+
+    cmd.args(mock_args(addr));
+
+    cmd
+}
+
+/// Returns a fake registry API serving the SDK fixtures, where `mynamespace/myimage` has the tags
+/// of `image_tag_list.json` and every tag can be deleted
+pub fn registry() -> server::Server {
+    registry_recording_deletes(Arc::default())
+}
+
+/// Returns the fake registry API of `registry`, pushing the time of every delete request it
+/// receives onto `deletes`
+pub fn registry_recording_deletes(deletes: Arc<Mutex<Vec<Instant>>>) -> server::Server {
+    server::http(move |req| {
+        if req.method() == "DELETE" {
+            deletes.lock().unwrap().push(Instant::now());
+        }
+
+        async move {
+            let body = match (req.method().as_str(), req.uri().path(), req.uri().query()) {
+                ("GET", "/namespaces", Some("page=1&page_size=100")) => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/namespace_list.json")
+                }
+                ("GET", "/images", Some("page=1&page_size=100")) => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                }
+                ("GET", "/images", Some("page=2&page_size=100")) => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/image_list_page_2.json")
+                }
+                ("GET", "/images", Some("page=3&page_size=100")) => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/image_list_page_3.json")
+                }
+                ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags", _) => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/image_tag_list.json")
+                }
+                ("DELETE", path, _) if path.starts_with("/tags/") => {
+                    include_str!("../../../scaleway_sdk/tests/fixtures/image_tag.json")
+                }
+                (method, path, query) => {
+                    panic!("unexpected request: {} {} {:?}", method, path, query)
+                }
+            };
+
+            http::Response::builder().body(body.into()).unwrap()
+        }
+    })
+}
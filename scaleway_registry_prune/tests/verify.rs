@@ -0,0 +1,22 @@
+mod support;
+
+#[test]
+fn it_warns_about_tags_that_survive_deletion() {
+    // The fake registry keeps listing every tag after it has been deleted
+    let server = support::registry();
+    let output = support::command(server.addr())
+        .args([
+            "mynamespace/myimage",
+            "--keep-last",
+            "25",
+            "--yes",
+            "--verify",
+        ])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("Warning: myimage:168fbd0 still exists after being deleted"));
+    assert!(stderr.contains("Verified 0 deleted tags, 2 still present"));
+}
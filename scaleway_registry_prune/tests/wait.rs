@@ -0,0 +1,77 @@
+mod support;
+use support::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const TAG_PATH: &str = "/tags/219513c9-d144-4e84-ac48-c4621fca43ed";
+
+/// Returns a fake registry API where the only deleted tag is still being deleted the first
+/// `polls` times it's looked up, and gone afterwards
+fn registry_deleting_for(polls: usize, lookups: Arc<AtomicUsize>) -> server::Server {
+    server::http(move |req| {
+        let lookups = lookups.clone();
+
+        async move {
+            let deleting = include_str!("../../scaleway_sdk/tests/fixtures/image_tag.json")
+                .replace(r#""status": "ready""#, r#""status": "deleting""#);
+            let body = match (req.method().as_str(), req.uri().path()) {
+                ("GET", "/namespaces") => {
+                    include_str!("../../scaleway_sdk/tests/fixtures/namespace_list.json").to_owned()
+                }
+                ("GET", "/images") => {
+                    include_str!("../../scaleway_sdk/tests/fixtures/image_list_page_1.json")
+                        .to_owned()
+                }
+                ("GET", "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags") => {
+                    include_str!("../../scaleway_sdk/tests/fixtures/image_tag_list.json").to_owned()
+                }
+                ("DELETE", TAG_PATH) => deleting,
+                ("GET", TAG_PATH) if lookups.fetch_add(1, Ordering::SeqCst) < polls => deleting,
+                ("GET", TAG_PATH) => {
+                    return http::Response::builder()
+                        .status(404)
+                        .body(r#"{"message":"resource is not found"}"#.into())
+                        .unwrap();
+                }
+                (method, path) => panic!("unexpected request: {} {}", method, path),
+            };
+
+            http::Response::builder().body(body.into()).unwrap()
+        }
+    })
+}
+
+fn prune(server: &server::Server, args: &[&str]) -> std::process::Output {
+    support::command(server.addr())
+        .args(["mynamespace/myimage", "--tag", "5dbc258", "--yes", "--wait"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_waits_for_a_deleting_tag_to_be_gone() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let server = registry_deleting_for(1, lookups.clone());
+    let output = prune(&server, &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("across 1 tags"), "{}", stderr);
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn it_fails_tags_still_being_deleted_after_the_timeout() {
+    let server = registry_deleting_for(usize::MAX, Arc::default());
+    let output = prune(&server, &["--wait-timeout", "1s"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(6), "{}", stderr);
+    assert!(
+        stderr.contains("still being deleted after 1s"),
+        "{}",
+        stderr
+    );
+}
@@ -1,15 +1,47 @@
-use failure::Fail;
+use thiserror::Error;
 
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum Error {
-    #[fail(display = "HTTP client error: {}", _0)]
-    ReqwestError(#[fail(cause)] reqwest::Error),
-    #[fail(display = "API error: {}", _0)]
+    #[error("HTTP client error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("API error: {0}")]
     ApiError(String),
+    /// The API responded to the request for `path` with a body that couldn't be parsed, of which
+    /// `snippet` is the beginning
+    #[error("Unable to parse the API response of {path}: {source} (body: {snippet})")]
+    JsonError {
+        path: String,
+        snippet: String,
+        source: serde_json::Error,
+    },
+    /// The API responded with a body larger than the maximum response size
+    #[error("The API response is larger than the maximum of {limit} bytes")]
+    ResponseTooLarge { limit: usize },
+    /// The API responded with a non-successful HTTP `status` and an error `message`, along with
+    /// the ID of the request from the `X-Request-Id` header if present
+    #[error("API error ({status}): {message}{}", request_id_suffix(.request_id))]
+    Http {
+        status: reqwest::StatusCode,
+        message: String,
+        request_id: Option<String>,
+    },
+    /// The API refused a conditional deletion because the tag no longer has the expected digest
+    #[error("The tag {tag_id} changed since it was listed")]
+    TagChanged { tag_id: String },
+    #[error("No such namespace")]
+    NoSuchNamespace,
+    #[error("No such image")]
+    NoSuchImage,
+    #[error("No such tag")]
+    NoSuchTag,
+    #[error("Invalid visibility: {0}, expected one of inherit, public or private")]
+    InvalidVisibility(String),
 }
 
-impl From<reqwest::Error> for Error {
-    fn from(err: reqwest::Error) -> Error {
-        Error::ReqwestError(err)
+/// Returns the request ID to append to an error message, if any
+fn request_id_suffix(request_id: &Option<String>) -> String {
+    match request_id {
+        Some(id) => format!(" (request id: {})", id),
+        None => String::new(),
     }
 }
@@ -4,3 +4,4 @@ mod status;
 
 pub use error::Error;
 pub use registry::Registry;
+pub use reqwest::StatusCode;
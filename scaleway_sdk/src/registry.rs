@@ -1,22 +1,78 @@
 use std::cmp::Ordering;
-use std::time::Duration as StdDuration;
+use std::fmt;
+use std::time::{Duration as StdDuration, Instant};
 
-use chrono::{DateTime, Utc};
-use serde::{de::DeserializeOwned, Deserialize};
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use log::{debug, warn};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub use crate::status::Status;
 use crate::Error;
 
+/// The regions where the registry API is available
+///
+/// The API has no endpoint listing its regions, so this has to be kept up to date by hand as
+/// Scaleway opens new regions.
+pub const REGIONS: &[&str] = &["fr-par", "nl-ams", "pl-waw"];
+
 static DEFAULT_API_ENDPOINT: &str = "https://api.scaleway.com/registry/v1";
 
+/// The default number of items requested per page when listing resources
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// The default timeout for each request
+const DEFAULT_TIMEOUT: StdDuration = StdDuration::from_secs(30);
+
+/// The default number of times a rate limited request is retried
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// The maximum number of namespace details requested at the same time by
+/// [`Registry::namespaces_with_sizes`]
+const NAMESPACE_DETAIL_CONCURRENCY: usize = 4;
+
+/// The delay before the first retry of a rate limited request, doubled on every following retry
+const RETRY_BASE_DELAY: StdDuration = StdDuration::from_millis(500);
+
+/// The longest delay between retries, which the doubling of `RETRY_BASE_DELAY` is capped at
+const MAX_RETRY_DELAY: StdDuration = StdDuration::from_secs(60);
+
+/// The maximum number of characters of a response body included in `Error::JsonError`
+const BODY_SNIPPET_LENGTH: usize = 200;
+
+/// The default maximum size of a response body
+const DEFAULT_MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The visibilities accepted by [`Registry::update_image`]
+const IMAGE_VISIBILITIES: [&str; 3] = ["inherit", "public", "private"];
+
+/// A client for the registry API of a single region
+///
+/// Cloning a `Registry` is cheap, as the clones share the same connection pool.
+#[derive(Clone)]
 pub struct Registry {
     client: reqwest::Client,
     region: String,
     endpoint: String,
+    /// Whether `endpoint` was set explicitly rather than composed from the region
+    custom_endpoint: bool,
     auth_token: String,
+    page_size: usize,
+    max_retries: u32,
+    /// Whether the delay between retries is randomized
+    jitter: bool,
+    idempotent_deletes: bool,
+    /// The timeout the client was built with, which is unknown for a client given to
+    /// `with_client`
+    timeout: Option<StdDuration>,
+    proxy: Option<reqwest::Proxy>,
+    organization_id: Option<String>,
+    project_id: Option<String>,
+    max_response_size: usize,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Namespace {
     id: String,
     name: String,
@@ -33,7 +89,7 @@ pub struct Namespace {
     image_count: usize,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Image {
     id: String,
     name: String,
@@ -48,6 +104,10 @@ pub struct Image {
     tags: Vec<String>,
 }
 
+/// A tag of an image, pointing at a manifest `digest`
+///
+/// The API doesn't report the size of individual tags, only the total size of an [`Image`], so
+/// the storage freed by deleting tags can only be estimated from it.
 #[derive(Deserialize, Debug, Clone, Eq)]
 pub struct ImageTag {
     id: String,
@@ -102,6 +162,18 @@ impl ImageTag {
     pub fn is_newer_than(&self, date_time: DateTime<Utc>) -> bool {
         self.updated_at >= date_time
     }
+
+    /// Returns the time elapsed since this tag was last updated
+    pub fn age(&self) -> Duration {
+        self.age_at(Utc::now())
+    }
+
+    /// Returns the time elapsed between the last time this tag was updated and `now`
+    ///
+    /// The duration is negative if the tag was updated after `now`, e.g. due to clock skew
+    pub fn age_at(&self, now: DateTime<Utc>) -> Duration {
+        now.signed_duration_since(self.updated_at)
+    }
 }
 
 impl Ord for ImageTag {
@@ -154,9 +226,10 @@ impl Namespace {
     /// Returns the total size for all images in this namespace
     ///
     /// Note that the size is only present if the namespace is retrieved using
-    /// [`Registry::namespace`]
+    /// [`Registry::namespace`] or [`Registry::namespaces_with_sizes`]
     ///
     /// [`Registry::namespace`]: struct.Registry.html#method.namespace
+    /// [`Registry::namespaces_with_sizes`]: struct.Registry.html#method.namespaces_with_sizes
     pub fn size(&self) -> Option<usize> {
         self.size
     }
@@ -258,39 +331,273 @@ impl Image {
     }
 }
 
+#[derive(Serialize, Debug)]
+struct CreateNamespaceRequest<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    is_public: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct UpdateImageRequest<'a> {
+    visibility: &'a str,
+}
+
 #[derive(Deserialize, Debug)]
 struct ErrorMessage {
     message: String,
 }
 
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("region", &self.region)
+            .field("endpoint", &self.endpoint)
+            .field("custom_endpoint", &self.custom_endpoint)
+            .field("auth_token", &"<redacted>")
+            .field("page_size", &self.page_size)
+            .field("max_retries", &self.max_retries)
+            .field("jitter", &self.jitter)
+            .field("idempotent_deletes", &self.idempotent_deletes)
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("organization_id", &self.organization_id)
+            .field("project_id", &self.project_id)
+            .field("max_response_size", &self.max_response_size)
+            .finish()
+    }
+}
+
 impl Registry {
     /// Creates a new `Registry` API instance
+    ///
+    /// The `auth_token` is either a legacy authentication token or the secret key of an API key,
+    /// as both are sent in the `X-Auth-Token` header
     pub fn new(auth_token: String, region: String) -> Self {
-        let client = reqwest::ClientBuilder::new()
-            .timeout(StdDuration::from_secs(30))
-            .build()
-            .unwrap();
+        Registry {
+            timeout: Some(DEFAULT_TIMEOUT),
+            ..Self::with_client(build_client(DEFAULT_TIMEOUT, None), auth_token, region)
+        }
+    }
 
+    /// Creates a new `Registry` API instance that sends its requests through `client`
+    ///
+    /// As a `reqwest::Client` is cheap to clone, this lets several instances - e.g. one per
+    /// region - share a single connection pool. The timeout and other settings of `client` apply
+    /// to every request.
+    ///
+    /// Calling `timeout` or `proxy` afterwards replaces `client` with a new client of its own,
+    /// which no longer shares the connection pool or any other setting of `client`.
+    pub fn with_client(client: reqwest::Client, auth_token: String, region: String) -> Self {
         Registry {
             client,
-            endpoint: format!("{}/regions/{}", DEFAULT_API_ENDPOINT, region),
+            endpoint: regional_endpoint(DEFAULT_API_ENDPOINT, &region),
+            custom_endpoint: false,
             auth_token,
             region,
+            page_size: DEFAULT_PAGE_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
+            jitter: true,
+            idempotent_deletes: false,
+            timeout: None,
+            proxy: None,
+            organization_id: None,
+            project_id: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
         }
     }
 
     /// Sets endpoint `url` by mutating self
+    ///
+    /// The `url` replaces the regional endpoint as a whole, so request paths are appended to it
+    /// directly without a `/regions/{region}` prefix.
     pub fn endpoint(mut self, url: &str) -> Self {
-        self.endpoint = url.to_string();
+        self.endpoint = url.trim_end_matches('/').to_string();
+        self.custom_endpoint = true;
+        self
+    }
+
+    /// Switches to `region` by mutating self, so that one client can be reused across regions
+    ///
+    /// The regional endpoint is composed again for the new region, unless an endpoint was set
+    /// explicitly with [`Registry::endpoint`], in which case it's left as is.
+    pub fn set_region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+
+        if !self.custom_endpoint {
+            self.endpoint = regional_endpoint(DEFAULT_API_ENDPOINT, region);
+        }
+
+        self
+    }
+
+    /// Returns the region this instance was created for
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Returns the full URL of the given `path` on the API endpoint
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.endpoint, path)
+    }
+
+    /// Sets the `timeout` for each request by mutating self
+    ///
+    /// The underlying HTTP client is rebuilt with the new timeout, replacing any client given to
+    /// `with_client`
+    pub fn timeout(mut self, timeout: StdDuration) -> Self {
+        self.timeout = Some(timeout);
+        self.client = build_client(timeout, self.proxy.clone());
+        self
+    }
+
+    /// Sends every request through the proxy at `url` by mutating self
+    ///
+    /// The underlying HTTP client is rebuilt with the proxy, replacing any client given to
+    /// `with_client`, and with the default timeout unless one was set. Returns an error if `url`
+    /// isn't a valid proxy URL.
+    pub fn proxy(mut self, url: &str) -> Result<Self, Error> {
+        let proxy = reqwest::Proxy::all(url)?;
+        let timeout = *self.timeout.get_or_insert(DEFAULT_TIMEOUT);
+
+        self.client = build_client(timeout, Some(proxy.clone()));
+        self.proxy = Some(proxy);
+
+        Ok(self)
+    }
+
+    /// Only lists the namespaces and images of the organization with the given `id` by mutating
+    /// self
+    ///
+    /// Without it, the listings are scoped by the defaults of the token
+    pub fn organization_id(mut self, id: &str) -> Self {
+        self.organization_id = Some(id.to_string());
+        self
+    }
+
+    /// Only lists the namespaces and images of the project with the given `id` by mutating self
+    ///
+    /// Without it, the listings are scoped by the defaults of the token
+    pub fn project_id(mut self, id: &str) -> Self {
+        self.project_id = Some(id.to_string());
+        self
+    }
+
+    /// Sets the number of items requested per page when listing resources by mutating self
+    ///
+    /// A page size of 0 is clamped to 1
+    pub fn page_size(mut self, n: usize) -> Self {
+        self.page_size = n.max(1);
+        self
+    }
+
+    /// Sets the maximum size in bytes of a response body by mutating self
+    ///
+    /// Larger responses are rejected with `Error::ResponseTooLarge` instead of being read into
+    /// memory
+    pub fn max_response_size(mut self, size: usize) -> Self {
+        self.max_response_size = size;
+        self
+    }
+
+    /// Sets the maximum number of times a request is retried when the API responds with
+    /// `429 Too Many Requests` or `503 Service Unavailable` by mutating self
+    pub fn max_retries(mut self, n: u32) -> Self {
+        self.max_retries = n;
+        self
+    }
+
+    /// Sets whether the delay between retries is randomized by mutating self
+    ///
+    /// With jitter, which is the default, each delay is picked at random between zero and the
+    /// exponential backoff, so that clients that were rate limited together don't all retry at
+    /// the same time. Disabling it makes the delays predictable, e.g. for tests.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Returns the delay before the retry following `retries` earlier retries, when the API
+    /// doesn't ask for a delay of its own with a `Retry-After` header
+    ///
+    /// The delay doubles with every retry, up to a maximum of 60 seconds.
+    pub fn retry_delay(&self, retries: u32) -> StdDuration {
+        let backoff = RETRY_BASE_DELAY
+            .checked_mul(2u32.saturating_pow(retries))
+            .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY));
+
+        if self.jitter {
+            rand::thread_rng().gen_range(StdDuration::ZERO..=backoff)
+        } else {
+            backoff
+        }
+    }
+
+    /// Sets whether deleting a tag that no longer exists succeeds by mutating self
+    ///
+    /// This makes it safe to retry a deletion that may already have gone through, as the tag
+    /// being gone is the desired outcome either way
+    pub fn idempotent_deletes(mut self, idempotent: bool) -> Self {
+        self.idempotent_deletes = idempotent;
         self
     }
 
     /// Returns a list of namespaces the user has access to
+    ///
+    /// All pages are requested in turn until `total_count` namespaces have been retrieved
     pub async fn namespaces(&self) -> Result<Vec<Namespace>, Error> {
-        // FIXME: Implement proper page handling
-        self.get_deserialized::<NamespaceListResponse>("/namespaces")
+        let mut namespaces = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let res = self
+                .get_scoped_page::<NamespaceListResponse>("/namespaces", page)
+                .await?;
+            let is_last_page = res.namespaces.is_empty();
+
+            namespaces.extend(res.namespaces);
+
+            if is_last_page || namespaces.len() >= res.total_count {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(namespaces)
+    }
+
+    /// Returns a list of namespaces the user has access to, with their `size` present
+    ///
+    /// The list endpoint doesn't include the size of each namespace, so the details of every
+    /// namespace are requested as well, a few at a time.
+    pub async fn namespaces_with_sizes(&self) -> Result<Vec<Namespace>, Error> {
+        let namespaces = self.namespaces().await?;
+
+        stream::iter(namespaces)
+            .map(|namespace| async move {
+                if namespace.size.is_some() {
+                    Ok(namespace)
+                } else {
+                    self.namespace(&namespace.id).await
+                }
+            })
+            .buffered(NAMESPACE_DETAIL_CONCURRENCY)
+            .try_collect()
             .await
-            .map(|x| x.namespaces)
+    }
+
+    /// Returns the namespace with the given `name`
+    ///
+    /// Returns `Error::NoSuchNamespace` if none of the namespaces the user has access to has that
+    /// name
+    pub async fn namespace_by_name(&self, name: &str) -> Result<Namespace, Error> {
+        self.namespaces()
+            .await?
+            .into_iter()
+            .find(|namespace| namespace.name == name)
+            .ok_or(Error::NoSuchNamespace)
     }
 
     /// Returns the namespace details for a given `namespace_id`
@@ -300,37 +607,165 @@ impl Registry {
     }
 
     /// Returns a list of all images accessible to the user
+    ///
+    /// All pages are requested in turn until `total_count` images have been retrieved
     pub async fn images(&self) -> Result<Vec<Image>, Error> {
-        // FIXME: Implement proper page handling
-        self.get_deserialized::<ImageListResponse>("/images")
-            .await
-            .map(|x| x.images)
+        let mut images = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let res = self
+                .get_scoped_page::<ImageListResponse>("/images", page)
+                .await?;
+            let is_last_page = res.images.is_empty();
+
+            images.extend(res.images);
+
+            if is_last_page || images.len() >= res.total_count {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(images)
     }
 
-    /// Retrieves all tags for a given `image` and returns them
-    pub async fn image_tags(&self, image_id: &str) -> Result<Vec<ImageTag>, Error> {
-        // FIXME: Implement proper page handling
+    /// Returns the image details for a given `image_id`
+    ///
+    /// Returns `Error::NoSuchImage` if the image doesn't exist
+    pub async fn image(&self, image_id: &str) -> Result<Image, Error> {
         let res = self
-            .get(&format!("/images/{}/tags", image_id))
-            .query(&[("page_size", "100")])
-            .send()
+            .send(self.get(&format!("/images/{}", image_id)))
             .await?;
 
-        if res.status().is_success() {
-            res.json::<ImageTagListResponse>()
-                .await
-                .map_err(Into::into)
-                .map(|x| x.tags)
-        } else {
-            let err = res.json::<ErrorMessage>().await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::NoSuchImage);
+        }
+
+        self.deserialize_response(res).await
+    }
+
+    /// Retrieves the first page of tags for a given `image` and returns them
+    ///
+    /// Use [`Registry::image_tags_stream`] or [`Registry::image_tags_all`] to retrieve the tags of
+    /// every page.
+    pub async fn image_tags(&self, image_id: &str) -> Result<Vec<ImageTag>, Error> {
+        let req = self
+            .get(&format!("/images/{}/tags", image_id))
+            .query(&[("page_size", self.page_size)]);
+
+        self.send_deserialized::<ImageTagListResponse>(req)
+            .await
+            .map(|x| x.tags)
+    }
+
+    /// Returns a stream of all tags for a given `image`, walking every page
+    ///
+    /// A page is only requested once the tags of the previous page have been consumed, and the
+    /// stream ends after `total_count` tags or at the first error.
+    pub fn image_tags_stream<'a>(
+        &'a self,
+        image_id: &str,
+    ) -> impl Stream<Item = Result<ImageTag, Error>> + 'a {
+        let path = format!("/images/{}/tags", image_id);
+
+        // The state is the next page to request, if any, along with the number of tags so far
+        stream::try_unfold((Some(1), 0), move |(page, count)| {
+            let path = path.clone();
+
+            async move {
+                let page = match page {
+                    Some(page) => page,
+                    None => return Ok(None),
+                };
+                let res = self
+                    .get_deserialized_page::<ImageTagListResponse>(&path, page)
+                    .await?;
+                let count = count + res.tags.len();
+                let next_page = if res.tags.is_empty() || count >= res.total_count {
+                    None
+                } else {
+                    Some(page + 1)
+                };
+                let tags = stream::iter(res.tags.into_iter().map(Ok));
+
+                Ok::<_, Error>(Some((tags, (next_page, count))))
+            }
+        })
+        .try_flatten()
+    }
+
+    /// Retrieves all tags for a given `image` and returns them
+    ///
+    /// This collects [`Registry::image_tags_stream`], so every page is fetched before returning.
+    pub async fn image_tags_all(&self, image_id: &str) -> Result<Vec<ImageTag>, Error> {
+        self.image_tags_stream(image_id).try_collect().await
+    }
+
+    /// Retrieves all tags for a given `image` that are in the given `status` and returns them
+    ///
+    /// The API can't filter tags by status, so every page is fetched and the tags are filtered
+    /// client-side.
+    pub async fn image_tags_by_status(
+        &self,
+        image_id: &str,
+        status: Status,
+    ) -> Result<Vec<ImageTag>, Error> {
+        let mut tags = self.image_tags_all(image_id).await?;
+
+        tags.retain(|tag| tag.status == status);
+
+        Ok(tags)
+    }
+
+    /// Returns the tag details for a given `tag_id`
+    ///
+    /// Returns `Error::NoSuchTag` if the tag doesn't exist
+    pub async fn image_tag(&self, tag_id: &str) -> Result<ImageTag, Error> {
+        let res = self.send(self.get(&format!("/tags/{}", tag_id))).await?;
 
-            Err(Error::ApiError(err.message))
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::NoSuchTag);
         }
+
+        self.deserialize_response(res).await
     }
 
     /// Deletes an image with the given `image_tag` if it exists - the operation will fail if two
     /// tags share the same digest unless `force` is true
-    pub async fn delete_image_by_tag(&self, tag_id: &str, force: bool) -> Result<ImageTag, Error> {
+    ///
+    /// Returns the tag as it was just before deletion, or `None` if the tag doesn't exist and
+    /// `idempotent_deletes` is set
+    pub async fn delete_image_by_tag(
+        &self,
+        tag_id: &str,
+        force: bool,
+    ) -> Result<Option<ImageTag>, Error> {
+        self.delete_tag(tag_id, force, None).await
+    }
+
+    /// Deletes the tag with the given `tag_id` like [`Registry::delete_image_by_tag`], as long as
+    /// it still has the given `digest`
+    ///
+    /// The digest is sent in an `If-Match` header, so that a tag that was pushed again after it
+    /// was listed isn't deleted. Returns `Error::TagChanged` if the API responds with
+    /// `412 Precondition Failed`.
+    pub async fn delete_image_by_tag_if_match(
+        &self,
+        tag_id: &str,
+        digest: &str,
+        force: bool,
+    ) -> Result<Option<ImageTag>, Error> {
+        self.delete_tag(tag_id, force, Some(digest)).await
+    }
+
+    async fn delete_tag(
+        &self,
+        tag_id: &str,
+        force: bool,
+        if_match: Option<&str>,
+    ) -> Result<Option<ImageTag>, Error> {
         // FIXME: deal with force properly
         let mut req = self.delete(&format!("/tags/{}", tag_id));
 
@@ -338,36 +773,241 @@ impl Registry {
             req = req.query(&[("force", "true")]);
         }
 
-        let res = req.send().await?;
+        if let Some(digest) = if_match {
+            req = req.header(reqwest::header::IF_MATCH, format!("\"{}\"", digest));
+        }
 
-        if res.status().is_success() {
-            res.json::<ImageTag>().await.map_err(Into::into)
-        } else {
-            let err = res.json::<ErrorMessage>().await?;
+        let res = self.send(req).await?;
+
+        if if_match.is_some() && res.status() == reqwest::StatusCode::PRECONDITION_FAILED {
+            return Err(Error::TagChanged {
+                tag_id: tag_id.to_owned(),
+            });
+        }
+
+        if self.idempotent_deletes && res.status() == reqwest::StatusCode::NOT_FOUND {
+            debug!("Tag {} is already deleted", tag_id);
+
+            return Ok(None);
+        }
+
+        self.deserialize_response(res).await.map(Some)
+    }
 
-            Err(Error::ApiError(err.message))
+    /// Creates a namespace with the given `name` and `description`, which is public if
+    /// `is_public` is true, and returns it
+    pub async fn create_namespace(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        is_public: bool,
+    ) -> Result<Namespace, Error> {
+        let body = CreateNamespaceRequest {
+            name,
+            description,
+            is_public,
+        };
+
+        self.send_deserialized(self.post("/namespaces").json(&body))
+            .await
+    }
+
+    /// Sets the visibility of the image with the given `image_id` to `visibility`, which is one
+    /// of `inherit`, `public` or `private`, and returns the updated image
+    ///
+    /// Returns `Error::InvalidVisibility` without sending a request if `visibility` isn't one of
+    /// the accepted values
+    pub async fn update_image(&self, image_id: &str, visibility: &str) -> Result<Image, Error> {
+        if !IMAGE_VISIBILITIES.contains(&visibility) {
+            return Err(Error::InvalidVisibility(visibility.to_string()));
         }
+
+        let body = UpdateImageRequest { visibility };
+
+        self.send_deserialized(self.patch(&format!("/images/{}", image_id)).json(&body))
+            .await
+    }
+
+    /// Deletes the image with the given `image_id` along with all of its tags and returns the
+    /// image as it was just before deletion
+    pub async fn delete_image(&self, image_id: &str) -> Result<Image, Error> {
+        self.send_deserialized(self.delete(&format!("/images/{}", image_id)))
+            .await
+    }
+
+    /// Deletes the namespace with the given `namespace_id` along with all of its images and
+    /// returns the namespace as it was just before deletion
+    pub async fn delete_namespace(&self, namespace_id: &str) -> Result<Namespace, Error> {
+        self.send_deserialized(self.delete(&format!("/namespaces/{}", namespace_id)))
+            .await
     }
 
     /// Requests the given `path` on the API endpoint and tries to deserialize
     /// it as json into the type `D`.
     async fn get_deserialized<D: DeserializeOwned>(&self, path: &str) -> Result<D, Error> {
-        let res = self.get(path).send().await?;
+        self.send_deserialized(self.get(path)).await
+    }
+
+    /// Requests the given `page` of the list at `path` on the API endpoint and
+    /// tries to deserialize it as json into the type `D`.
+    async fn get_deserialized_page<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        page: usize,
+    ) -> Result<D, Error> {
+        let req = self
+            .get(path)
+            .query(&[("page", page), ("page_size", self.page_size)]);
+
+        self.send_deserialized(req).await
+    }
+
+    /// Requests the given `page` of the list at `path` like `get_deserialized_page`, scoped to
+    /// the organization and project if set
+    async fn get_scoped_page<D: DeserializeOwned>(
+        &self,
+        path: &str,
+        page: usize,
+    ) -> Result<D, Error> {
+        let mut scope = Vec::new();
+
+        if let Some(id) = &self.organization_id {
+            scope.push(("organization_id", id));
+        }
+
+        if let Some(id) = &self.project_id {
+            scope.push(("project_id", id));
+        }
+
+        let req = self
+            .get(path)
+            .query(&[("page", page), ("page_size", self.page_size)])
+            .query(&scope);
+
+        self.send_deserialized(req).await
+    }
 
-        if res.status().is_success() {
-            res.json::<D>().await.map_err(Into::into)
+    /// Sends the prepared request `req` and tries to deserialize the response
+    /// as json into the type `D`, or returns the HTTP status and API error message.
+    async fn send_deserialized<D: DeserializeOwned>(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<D, Error> {
+        self.deserialize_response(self.send(req).await?).await
+    }
+
+    /// Sends the prepared request `req` and returns the response
+    ///
+    /// While the API responds with `429 Too Many Requests` or `503 Service Unavailable`, the
+    /// request is retried up to `max_retries` times. The delay between retries is taken from the
+    /// `Retry-After` header if present, and otherwise doubles with every retry, with jitter unless
+    /// disabled.
+    async fn send(&self, req: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let req = req.build()?;
+        let mut retries = 0;
+
+        loop {
+            let start = Instant::now();
+            let res = self
+                .client
+                .execute(req.try_clone().expect("request body can't be cloned"))
+                .await?;
+            let status = res.status();
+
+            // Only the method and URL are logged, as the headers carry the auth token
+            debug!(
+                "{} {} returned {} in {:?}",
+                req.method(),
+                req.url(),
+                status,
+                start.elapsed()
+            );
+
+            let is_retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::SERVICE_UNAVAILABLE;
+
+            if !is_retryable || retries >= self.max_retries {
+                return Ok(res);
+            }
+
+            let delay = retry_after(&res).unwrap_or_else(|| self.retry_delay(retries));
+
+            warn!(
+                "{} {} returned {}, retrying in {:?} ({}/{})",
+                req.method(),
+                req.url(),
+                status,
+                delay,
+                retries + 1,
+                self.max_retries
+            );
+
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// Tries to deserialize the response `res` as json into the type `D` if
+    /// it was successful, or returns the HTTP status, API error message and request ID otherwise.
+    async fn deserialize_response<D: DeserializeOwned>(
+        &self,
+        res: reqwest::Response,
+    ) -> Result<D, Error> {
+        let status = res.status();
+        let path = res.url().path().to_owned();
+
+        if status.is_success() {
+            let body = self.read_body(res).await?;
+
+            parse_body(&path, &body)
         } else {
-            let err = res.json::<ErrorMessage>().await?;
+            // The header has to be read before the body consumes the response
+            let request_id = res
+                .headers()
+                .get("X-Request-Id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned);
+            let body = self.read_body(res).await?;
+            let err = parse_body::<ErrorMessage>(&path, &body)?;
+
+            Err(Error::Http {
+                status,
+                message: err.message,
+                request_id,
+            })
+        }
+    }
 
-            Err(Error::ApiError(err.message))
+    /// Reads the body of the response `res` into memory, or returns `Error::ResponseTooLarge` as
+    /// soon as it's known to exceed `max_response_size`
+    ///
+    /// The advertised `Content-Length` is checked up front, and the body is read a chunk at a time
+    /// in case the length isn't given or is wrong.
+    async fn read_body(&self, mut res: reqwest::Response) -> Result<Vec<u8>, Error> {
+        let limit = self.max_response_size;
+
+        if res.content_length().is_some_and(|len| len > limit as u64) {
+            return Err(Error::ResponseTooLarge { limit });
         }
+
+        let mut body = Vec::new();
+
+        while let Some(chunk) = res.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(Error::ResponseTooLarge { limit });
+            }
+
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body)
     }
 
     /// Returns a prepared `RequestBuilder` that is ready to issue a GET request
     /// to the given `path` and a `X-Auth-Token` header already set
     pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
         self.client
-            .get(&format!("{}{}", self.endpoint, path))
+            .get(self.url(path))
             .header("X-Auth-Token", &self.auth_token)
     }
 
@@ -375,7 +1015,80 @@ impl Registry {
     /// to the given `path` and a `X-Auth-Token` header already set
     pub fn delete(&self, path: &str) -> reqwest::RequestBuilder {
         self.client
-            .delete(&format!("{}{}", self.endpoint, path))
+            .delete(self.url(path))
             .header("X-Auth-Token", &self.auth_token)
     }
+
+    /// Returns a prepared `RequestBuilder` that is ready to issue a PATCH request
+    /// to the given `path` with a JSON body and a `X-Auth-Token` header already set
+    pub fn patch(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .patch(self.url(path))
+            .header("X-Auth-Token", &self.auth_token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+    }
+
+    /// Returns a prepared `RequestBuilder` that is ready to issue a POST request
+    /// to the given `path` with a JSON body and a `X-Auth-Token` header already set
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(self.url(path))
+            .header("X-Auth-Token", &self.auth_token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+    }
+}
+
+/// Returns the endpoint of the API in `region`, relative to the `base` URL of the API
+fn regional_endpoint(base: &str, region: &str) -> String {
+    format!("{}/regions/{}", base, region)
+}
+
+/// Returns a new HTTP client where each request times out after `timeout`, sending requests
+/// through `proxy` if given
+///
+/// Without an explicit proxy, the proxy is read from the `HTTP_PROXY`/`HTTPS_PROXY` environment
+/// variables.
+fn build_client(timeout: StdDuration, proxy: Option<reqwest::Proxy>) -> reqwest::Client {
+    // Listings of large accounts compress well, so ask for gzip-encoded responses
+    let mut builder = reqwest::ClientBuilder::new().timeout(timeout).gzip(true);
+
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+
+    builder.build().unwrap()
+}
+
+/// Deserializes the response `body` to the request for `path` as json into the type `D`
+///
+/// On failure, the error includes the beginning of the body, to tell what the API returned
+/// instead.
+fn parse_body<D: DeserializeOwned>(path: &str, body: &[u8]) -> Result<D, Error> {
+    serde_json::from_slice(body).map_err(|source| {
+        let body = String::from_utf8_lossy(body);
+        let mut snippet = body.chars().take(BODY_SNIPPET_LENGTH).collect::<String>();
+
+        if body.chars().count() > BODY_SNIPPET_LENGTH {
+            snippet.push_str("...");
+        }
+
+        Error::JsonError {
+            path: path.to_owned(),
+            snippet,
+            source,
+        }
+    })
+}
+
+/// Returns the delay requested by the `Retry-After` header of the response `res`, if any
+///
+/// Only the delay-seconds form of the header is supported.
+fn retry_after(res: &reqwest::Response) -> Option<StdDuration> {
+    res.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(StdDuration::from_secs)
 }
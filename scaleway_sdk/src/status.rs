@@ -1,16 +1,23 @@
+use std::convert::Infallible;
 use std::fmt;
 use std::str::FromStr;
 
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-// Currently the status for namespaces and images share the same values.
+// Currently the status for namespaces, images and tags share the same values.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Status {
     Unknown,
     Ready,
     Deleting,
+    /// The resource has been deleted, but is still returned by the API for a short while
+    Deleted,
+    /// The resource is being created or updated and is about to become ready
+    Transient,
     Error,
     Locked,
+    /// A status that this version of the SDK doesn't know about yet, as it was returned by the API
+    Other(String),
 }
 
 impl fmt::Display for Status {
@@ -20,39 +27,58 @@ impl fmt::Display for Status {
 }
 
 impl FromStr for Status {
-    type Err = &'static str;
+    type Err = Infallible;
 
     fn from_str(s: &str) -> Result<Status, Self::Err> {
         match s {
             "unknown" => Ok(Status::Unknown),
             "ready" => Ok(Status::Ready),
             "deleting" => Ok(Status::Deleting),
+            "deleted" => Ok(Status::Deleted),
+            "transient" => Ok(Status::Transient),
             "error" => Ok(Status::Error),
             "locked" => Ok(Status::Locked),
-            _ => Err("invalid status"),
+            other => Ok(Status::Other(other.to_owned())),
         }
     }
 }
 
 impl AsRef<str> for Status {
-    fn as_ref(&self) -> &'static str {
+    fn as_ref(&self) -> &str {
         match self {
             Status::Unknown => "unknown",
             Status::Ready => "ready",
             Status::Deleting => "deleting",
+            Status::Deleted => "deleted",
+            Status::Transient => "transient",
             Status::Error => "error",
             Status::Locked => "locked",
+            Status::Other(s) => s,
         }
     }
 }
 
+impl Serialize for Status {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
 impl Status {
+    /// Deserializes a status string, where any status that isn't known is kept as
+    /// `Status::Other` rather than failing the whole response
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Status, D::Error>
     where
         D: Deserializer<'de>,
     {
         let s: &str = Deserialize::deserialize(deserializer)?;
 
-        s.parse::<Status>().map_err(serde::de::Error::custom)
+        match s.parse::<Status>() {
+            Ok(status) => Ok(status),
+            Err(never) => match never {},
+        }
     }
 }
@@ -0,0 +1,54 @@
+mod support;
+use support::*;
+
+use scaleway_sdk::registry::Registry;
+
+use std::sync::Mutex;
+
+/// A logger that records every message so that tests can inspect them
+struct CapturingLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(format!("{}", record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    messages: Mutex::new(Vec::new()),
+};
+
+#[tokio::test]
+async fn it_logs_request_urls_without_the_auth_token() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .body(include_str!("fixtures/image_tag.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry =
+        Registry::new("super-secret-token".to_owned(), "region".to_owned()).endpoint(&endpoint);
+
+    registry.image_tag("some-tag-id").await.unwrap();
+
+    let messages = LOGGER.messages.lock().unwrap();
+    let url = format!("{}/tags/some-tag-id", endpoint);
+
+    assert!(messages.iter().any(|m| m.contains(&url)));
+    assert!(messages.iter().all(|m| !m.contains("super-secret-token")));
+}
@@ -2,15 +2,308 @@ mod support;
 use support::*;
 
 use scaleway_sdk::registry::{self, Status};
+use scaleway_sdk::Error;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{StreamExt, TryStreamExt};
+
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 fn new_registry(endpoint: &str) -> registry::Registry {
     registry::Registry::new("token".to_owned(), "region".to_owned()).endpoint(endpoint)
 }
 
+#[test]
+fn it_lists_the_regions_of_the_registry() {
+    for region in ["fr-par", "nl-ams", "pl-waw"] {
+        assert!(registry::REGIONS.contains(&region), "{} is missing", region);
+    }
+}
+
+#[test]
+fn it_composes_urls_from_the_regional_endpoint() {
+    let registry = registry::Registry::new("token".to_owned(), "nl-ams".to_owned());
+
+    assert_eq!(registry.region(), "nl-ams");
+    assert_eq!(
+        registry.url("/namespaces"),
+        "https://api.scaleway.com/registry/v1/regions/nl-ams/namespaces"
+    );
+}
+
+#[test]
+fn it_composes_urls_for_a_new_region() {
+    let registry = registry::Registry::new("token".to_owned(), "nl-ams".to_owned())
+        .page_size(10)
+        .set_region("fr-par");
+
+    assert_eq!(registry.region(), "fr-par");
+    assert_eq!(
+        registry.url("/namespaces"),
+        "https://api.scaleway.com/registry/v1/regions/fr-par/namespaces"
+    );
+}
+
+#[test]
+fn it_keeps_a_custom_endpoint_when_changing_region() {
+    let registry = registry::Registry::new("token".to_owned(), "nl-ams".to_owned())
+        .endpoint("http://localhost:8080/registry")
+        .set_region("fr-par");
+
+    assert_eq!(registry.region(), "fr-par");
+    assert_eq!(
+        registry.url("/namespaces"),
+        "http://localhost:8080/registry/namespaces"
+    );
+}
+
+#[test]
+fn it_composes_urls_from_a_custom_endpoint() {
+    let registry = registry::Registry::new("token".to_owned(), "nl-ams".to_owned())
+        .endpoint("http://localhost:8080/registry/");
+
+    assert_eq!(
+        registry.url("/namespaces"),
+        "http://localhost:8080/registry/namespaces"
+    );
+}
+
+#[tokio::test]
+async fn it_sends_requests_through_a_given_client() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["X-Client"], "shared");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("X-Client", "shared".parse().unwrap());
+
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let endpoint = format!("http://{}", server.addr());
+    let registry = registry::Registry::with_client(client, "token".to_owned(), "region".to_owned())
+        .endpoint(&endpoint);
+
+    assert_eq!(registry.namespaces().await.unwrap().len(), 1);
+}
+
+#[test]
+fn it_only_records_the_timeout_of_clients_it_builds() {
+    let registry = registry::Registry::with_client(
+        reqwest::Client::new(),
+        "token".to_owned(),
+        "region".to_owned(),
+    );
+
+    assert!(format!("{:?}", registry).contains("timeout: None"));
+    assert!(format!("{:?}", registry.timeout(Duration::from_secs(5))).contains("timeout: Some(5s)"));
+    assert!(format!("{:?}", new_registry("http://localhost")).contains("timeout: Some(30s)"));
+}
+
+#[tokio::test]
+async fn it_finds_namespace_by_name() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/namespaces?page=1&page_size=100");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespace = registry.namespace_by_name("mynamespace").await.unwrap();
+
+    assert_eq!(namespace.name(), "mynamespace");
+
+    let err = registry.namespace_by_name("nonexistent").await.unwrap_err();
+
+    assert!(matches!(err, Error::NoSuchNamespace));
+}
+
+#[tokio::test]
+async fn it_scopes_listings_to_organization_and_project() {
+    let server = server::http(move |req| async move {
+        let body = match req.uri().path() {
+            "/namespaces" => include_str!("fixtures/namespace_list.json"),
+            "/images" => include_str!("fixtures/image_list_mixed_visibility.json"),
+            path => panic!("unexpected request: {}", path),
+        };
+
+        assert_eq!(
+            req.uri().query(),
+            Some("page=1&page_size=100&organization_id=org&project_id=proj")
+        );
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint)
+        .organization_id("org")
+        .project_id("proj");
+
+    registry.namespaces().await.unwrap();
+    registry.images().await.unwrap();
+}
+
+#[tokio::test]
+async fn it_leaves_listings_unscoped_by_default() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/namespaces?page=1&page_size=100");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+
+    new_registry(&endpoint).namespaces().await.unwrap();
+}
+
+#[tokio::test]
+async fn it_decodes_gzip_encoded_responses() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["accept-encoding"], "gzip");
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(include_bytes!("fixtures/image_tag_list.json"))
+            .unwrap();
+
+        http::Response::builder()
+            .header("Content-Encoding", "gzip")
+            .body(encoder.finish().unwrap().into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let tags = registry
+        .image_tags("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap();
+
+    assert_eq!(tags.len(), 27);
+}
+
+#[tokio::test]
+async fn it_rejects_responses_advertising_an_oversized_body() {
+    let server = server::http(move |_req| async move {
+        // Valid JSON padded with whitespace to 1 MiB
+        let mut body = include_str!("fixtures/namespace_list.json").to_owned();
+        body.push_str(&" ".repeat(1024 * 1024 - body.len()));
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).max_response_size(1024);
+    let err = registry.namespaces().await.unwrap_err();
+
+    assert!(matches!(err, Error::ResponseTooLarge { limit: 1024 }));
+}
+
+#[tokio::test]
+async fn it_rejects_oversized_bodies_without_content_length() {
+    let server = server::http(move |_req| async move {
+        let chunks = vec![Ok::<_, std::io::Error>(include_str!(
+            "fixtures/image_tag_list.json"
+        ))];
+
+        http::Response::builder()
+            .body(hyper::Body::wrap_stream(futures::stream::iter(chunks)))
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).max_response_size(1024);
+    let err = registry
+        .image_tags("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::ResponseTooLarge { limit: 1024 }));
+}
+
+#[test]
+fn it_accepts_a_proxy() {
+    let registry = registry::Registry::new("token".to_owned(), "nl-ams".to_owned())
+        .proxy("http://proxy.example.com:3128")
+        .unwrap()
+        .timeout(Duration::from_secs(5));
+
+    assert!(format!("{:?}", registry).contains("proxy.example.com"));
+}
+
+#[test]
+fn it_rejects_a_malformed_proxy_url() {
+    let res = registry::Registry::new("token".to_owned(), "nl-ams".to_owned()).proxy("http://[::1");
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn it_redacts_the_auth_token_in_debug_output() {
+    let registry = registry::Registry::new("secret-token".to_owned(), "nl-ams".to_owned());
+    let debug = format!("{:?}", registry);
+
+    assert!(debug.contains("nl-ams"));
+    assert!(debug.contains("https://api.scaleway.com/registry/v1/regions/nl-ams"));
+    assert!(!debug.contains("secret-token"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_shares_the_endpoint_between_clones() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let requests = requests.clone();
+
+        move |req| {
+            let requests = requests.clone();
+
+            async move {
+                assert_eq!(req.uri(), "/namespaces?page=1&page_size=100");
+                requests.fetch_add(1, Ordering::SeqCst);
+
+                http::Response::builder()
+                    .body(include_str!("fixtures/namespace_list.json").into())
+                    .unwrap()
+            }
+        }
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let tasks = (0..2)
+        .map(|_| {
+            let registry = registry.clone();
+
+            tokio::spawn(async move { registry.namespaces().await.unwrap() })
+        })
+        .collect::<Vec<_>>();
+
+    for task in tasks {
+        assert_eq!(task.await.unwrap().len(), 1);
+    }
+
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
 #[tokio::test]
 async fn it_parses_namespace_list() {
     let server = server::http(move |req| async move {
-        assert_eq!(req.uri(), "/namespaces");
+        assert_eq!(req.uri(), "/namespaces?page=1&page_size=100");
 
         http::Response::builder()
             .body(include_str!("fixtures/namespace_list.json").into())
@@ -25,6 +318,50 @@ async fn it_parses_namespace_list() {
     assert_eq!(namespaces.first().unwrap().name(), "mynamespace");
 }
 
+#[tokio::test]
+async fn it_paginates_namespace_list() {
+    let server = server::http(move |req| async move {
+        let body = match req.uri().query() {
+            Some("page=1&page_size=100") => include_str!("fixtures/namespace_list_page_1.json"),
+            Some("page=2&page_size=100") => include_str!("fixtures/namespace_list_page_2.json"),
+            query => panic!("unexpected query: {:?}", query),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespaces = registry.namespaces().await.unwrap();
+
+    assert_eq!(namespaces.len(), 3);
+    assert_eq!(namespaces.last().unwrap().name(), "lastnamespace");
+}
+
+#[tokio::test]
+async fn it_paginates_image_list() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri().path(), "/images");
+
+        let body = match req.uri().query() {
+            Some("page=1&page_size=100") => include_str!("fixtures/image_list_page_1.json"),
+            Some("page=2&page_size=100") => include_str!("fixtures/image_list_page_2.json"),
+            Some("page=3&page_size=100") => include_str!("fixtures/image_list_page_3.json"),
+            query => panic!("unexpected query: {:?}", query),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let images = registry.images().await.unwrap();
+
+    assert_eq!(images.len(), 5);
+    assert_eq!(images.first().unwrap().name(), "myimage");
+    assert_eq!(images.last().unwrap().name(), "lastimage");
+}
+
 #[tokio::test]
 async fn it_parses_image_tag_list() {
     let server = server::http(move |req| async move {
@@ -49,3 +386,793 @@ async fn it_parses_image_tag_list() {
     assert_eq!(image_tags.first().unwrap().name(), "latest");
     assert_eq!(image_tags.first().unwrap().status(), Status::Ready);
 }
+
+#[tokio::test]
+async fn it_parses_image_tag_list_with_unknown_status() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .body(include_str!("fixtures/image_tag_list_unknown_status.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let statuses = registry
+        .image_tags("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap()
+        .iter()
+        .map(|t| t.status())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        statuses,
+        [
+            Status::Ready,
+            Status::Other("frobnicating".to_owned()),
+            Status::Deleted
+        ]
+    );
+}
+
+#[tokio::test]
+async fn it_filters_image_tags_by_status() {
+    let server = server::http(move |req| async move {
+        assert_eq!(
+            req.uri(),
+            "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags?page=1&page_size=100"
+        );
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image_tag_list_mixed_status.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let ready = registry
+        .image_tags_by_status("b00f6b0a-cc14-4c21-843f-3acda6ebb001", Status::Ready)
+        .await
+        .unwrap();
+    let deleting = registry
+        .image_tags_by_status("b00f6b0a-cc14-4c21-843f-3acda6ebb001", Status::Deleting)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        ready.iter().map(|t| t.name()).collect::<Vec<_>>(),
+        ["latest", "b6f0b34"]
+    );
+    assert_eq!(
+        deleting.iter().map(|t| t.name()).collect::<Vec<_>>(),
+        ["168fbd0"]
+    );
+}
+
+#[tokio::test]
+async fn it_paginates_image_tag_list() {
+    let server = server::http(move |req| async move {
+        assert_eq!(
+            req.uri().path(),
+            "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001/tags"
+        );
+
+        let body = match req.uri().query() {
+            Some("page=1&page_size=100") => include_str!("fixtures/image_tag_list_page_1.json"),
+            Some("page=2&page_size=100") => include_str!("fixtures/image_tag_list_page_2.json"),
+            query => panic!("unexpected query: {:?}", query),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let image_tags = registry
+        .image_tags_all("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap();
+
+    assert_eq!(image_tags.len(), 27);
+    assert_eq!(image_tags.first().unwrap().name(), "latest");
+}
+
+#[tokio::test]
+async fn it_streams_image_tags_page_by_page() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let requests = Arc::clone(&requests);
+
+        move |req| {
+            requests.fetch_add(1, Ordering::SeqCst);
+
+            async move {
+                let body = match req.uri().query() {
+                    Some("page=1&page_size=100") => {
+                        include_str!("fixtures/image_tag_list_page_1.json")
+                    }
+                    Some("page=2&page_size=100") => {
+                        include_str!("fixtures/image_tag_list_page_2.json")
+                    }
+                    query => panic!("unexpected query: {:?}", query),
+                };
+
+                http::Response::builder().body(body.into()).unwrap()
+            }
+        }
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let stream = registry.image_tags_stream("b00f6b0a-cc14-4c21-843f-3acda6ebb001");
+
+    futures::pin_mut!(stream);
+
+    let first = stream.next().await.unwrap().unwrap();
+
+    assert_eq!(first.name(), "latest");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let rest = stream.try_collect::<Vec<_>>().await.unwrap();
+
+    assert_eq!(rest.len(), 26);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn it_requests_the_configured_page_size() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/namespaces?page=1&page_size=25");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).page_size(25);
+
+    assert_eq!(registry.namespaces().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn it_clamps_a_zero_page_size_to_one() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/namespaces?page=1&page_size=1");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).page_size(0);
+
+    assert_eq!(registry.namespaces().await.unwrap().len(), 1);
+}
+
+// The details are requested over several connections, which need a worker thread to be closed
+// on while the server waits for them during shutdown
+#[tokio::test(flavor = "multi_thread")]
+async fn it_hydrates_namespace_sizes_from_details() {
+    let server = server::http(move |req| async move {
+        let body = match req.uri().path() {
+            "/namespaces" => include_str!("fixtures/namespace_list_without_sizes.json"),
+            "/namespaces/1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4" => {
+                include_str!("fixtures/namespace.json")
+            }
+            "/namespaces/1d4828c7-a0db-4acb-8d56-f2f4b19b8fc5" => {
+                include_str!("fixtures/namespace_other.json")
+            }
+            path => panic!("unexpected path: {}", path),
+        };
+
+        http::Response::builder().body(body.into()).unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespaces = registry.namespaces_with_sizes().await.unwrap();
+    let sizes = namespaces
+        .iter()
+        .map(|n| (n.name(), n.size()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        sizes,
+        [
+            ("mynamespace", Some(7205829924)),
+            ("othernamespace", Some(1048576))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn it_creates_namespace() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "POST");
+        assert_eq!(req.uri(), "/namespaces");
+        assert_eq!(req.headers()["content-type"], "application/json");
+        assert_eq!(req.headers()["x-auth-token"], "token");
+
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "name": "newnamespace",
+                "description": "Images for CI",
+                "is_public": true,
+            })
+        );
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_created.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespace = registry
+        .create_namespace("newnamespace", Some("Images for CI"), true)
+        .await
+        .unwrap();
+
+    assert_eq!(namespace.name(), "newnamespace");
+    assert!(namespace.is_public());
+}
+
+#[tokio::test]
+async fn it_updates_image_visibility() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "PATCH");
+        assert_eq!(req.uri(), "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001");
+        assert_eq!(req.headers()["content-type"], "application/json");
+        assert_eq!(req.headers()["x-auth-token"], "token");
+
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body, serde_json::json!({ "visibility": "private" }));
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image_private.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let image = registry
+        .update_image("b00f6b0a-cc14-4c21-843f-3acda6ebb001", "private")
+        .await
+        .unwrap();
+
+    assert_eq!(image.name(), "myimage");
+    assert_eq!(image.visibility(), "private");
+}
+
+#[tokio::test]
+async fn it_rejects_invalid_image_visibility() {
+    let server = server::http(move |req| async move {
+        panic!("unexpected request: {} {}", req.method(), req.uri());
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let result = registry
+        .update_image("b00f6b0a-cc14-4c21-843f-3acda6ebb001", "secret")
+        .await;
+
+    assert!(matches!(result, Err(Error::InvalidVisibility(v)) if v == "secret"));
+}
+
+#[tokio::test]
+async fn it_deletes_namespace() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "DELETE");
+        assert_eq!(
+            req.uri(),
+            "/namespaces/1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4"
+        );
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_deleting.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespace = registry
+        .delete_namespace("1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4")
+        .await
+        .unwrap();
+
+    assert_eq!(namespace.name(), "mynamespace");
+    assert_eq!(namespace.status(), Status::Deleting);
+}
+
+#[tokio::test]
+async fn it_returns_api_error_when_deleting_namespace_fails() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(404)
+            .body(r#"{"message":"resource is not found"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .delete_namespace("1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4")
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "API error (404 Not Found): resource is not found"
+    );
+    assert!(matches!(
+        err,
+        Error::Http { status, .. } if status == scaleway_sdk::StatusCode::NOT_FOUND
+    ));
+}
+
+#[tokio::test]
+async fn it_parses_image() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let image = registry
+        .image("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap();
+
+    assert_eq!(image.name(), "myimage");
+    assert_eq!(image.namespace_id(), "1d4828c7-a0db-4acb-8d56-f2f4b19b8fc4");
+}
+
+#[tokio::test]
+async fn it_deletes_image() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "DELETE");
+        assert_eq!(req.uri(), "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image_deleting.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let image = registry
+        .delete_image("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap();
+
+    assert_eq!(image.name(), "myimage");
+    assert_eq!(image.status(), Status::Deleting);
+}
+
+#[tokio::test]
+async fn it_returns_no_such_image_when_image_is_not_found() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(404)
+            .body(r#"{"message":"resource is not found"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .image("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::NoSuchImage));
+}
+
+#[tokio::test]
+async fn it_parses_image_tag() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/tags/5e3b5f16-afe2-44a4-93d5-3cbebee14960");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image_tag.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let image_tag = registry
+        .image_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960")
+        .await
+        .unwrap();
+
+    assert_eq!(image_tag.name(), "latest");
+    assert_eq!(
+        image_tag.updated_at(),
+        "2020-04-14T21:23:48.831047Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn it_includes_the_path_and_body_in_json_errors() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .body(r#"{"images": "unexpected"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .image("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::JsonError {
+            ref path,
+            ref snippet,
+            ..
+        } => {
+            assert_eq!(path, "/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001");
+            assert_eq!(snippet, r#"{"images": "unexpected"}"#);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    assert!(err
+        .to_string()
+        .contains("/images/b00f6b0a-cc14-4c21-843f-3acda6ebb001"));
+}
+
+#[tokio::test]
+async fn it_truncates_the_body_of_json_errors() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .body(format!("<html>{}</html>", "x".repeat(1000)).into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry.namespaces().await.unwrap_err();
+
+    match err {
+        Error::JsonError { snippet, .. } => {
+            assert!(snippet.starts_with("<html>xxx"));
+            assert_eq!(snippet.chars().count(), 203);
+            assert!(snippet.ends_with("..."));
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn it_returns_no_such_tag_when_tag_is_not_found() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(404)
+            .body(r#"{"message":"resource is not found"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .image_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::NoSuchTag));
+}
+
+#[test]
+fn it_parses_timestamps_of_fixtures() {
+    fn parse(s: &str) -> chrono::DateTime<chrono::Utc> {
+        s.parse().unwrap()
+    }
+
+    let namespace: registry::Namespace =
+        serde_json::from_str(include_str!("fixtures/namespace.json")).unwrap();
+    let image: registry::Image = serde_json::from_str(include_str!("fixtures/image.json")).unwrap();
+    let image_tag: registry::ImageTag =
+        serde_json::from_str(include_str!("fixtures/image_tag.json")).unwrap();
+
+    assert_eq!(namespace.created_at(), parse("2019-08-20T16:00:28.379816Z"));
+    assert_eq!(namespace.updated_at(), parse("2020-04-09T04:29:55.424234Z"));
+    assert_eq!(image.created_at(), parse("2019-08-28T12:52:30.621274Z"));
+    assert_eq!(image.updated_at(), parse("2020-04-14T21:23:48.831047Z"));
+    assert_eq!(image_tag.created_at(), parse("2019-08-28T12:52:30.621274Z"));
+    assert_eq!(image_tag.updated_at(), parse("2020-04-14T21:23:48.831047Z"));
+}
+
+#[test]
+fn it_returns_image_tag_age() {
+    let image_tag: registry::ImageTag =
+        serde_json::from_str(include_str!("fixtures/image_tag.json")).unwrap();
+    let updated_at = image_tag.updated_at();
+
+    assert_eq!(
+        image_tag.age_at(updated_at + chrono::Duration::days(42)),
+        chrono::Duration::days(42)
+    );
+    assert_eq!(
+        image_tag.age_at(updated_at - chrono::Duration::hours(1)),
+        chrono::Duration::hours(-1)
+    );
+}
+
+#[tokio::test]
+async fn it_retries_rate_limited_requests() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let requests = requests.clone();
+
+        move |_req| {
+            let attempt = requests.fetch_add(1, Ordering::SeqCst);
+
+            async move {
+                if attempt == 0 {
+                    http::Response::builder()
+                        .status(429)
+                        .header("Retry-After", "0")
+                        .body(r#"{"message":"rate limited"}"#.into())
+                        .unwrap()
+                } else {
+                    http::Response::builder()
+                        .body(include_str!("fixtures/namespace_list.json").into())
+                        .unwrap()
+                }
+            }
+        }
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let namespaces = registry.namespaces().await.unwrap();
+
+    assert_eq!(namespaces.len(), 1);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn it_doubles_the_retry_delay_without_jitter() {
+    let registry = new_registry("http://localhost").jitter(false);
+    let delays = (0..4).map(|n| registry.retry_delay(n)).collect::<Vec<_>>();
+
+    assert_eq!(
+        delays,
+        [
+            Duration::from_millis(500),
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+            Duration::from_secs(4)
+        ]
+    );
+}
+
+#[test]
+fn it_caps_the_retry_delay() {
+    let registry = new_registry("http://localhost").jitter(false);
+
+    assert_eq!(registry.retry_delay(7), Duration::from_secs(60));
+    assert_eq!(registry.retry_delay(40), Duration::from_secs(60));
+    assert!(new_registry("http://localhost").retry_delay(u32::MAX) <= Duration::from_secs(60));
+}
+
+#[test]
+fn it_keeps_jittered_retry_delays_within_the_backoff() {
+    let registry = new_registry("http://localhost");
+
+    for n in 0..4 {
+        let backoff = Duration::from_millis(500) * 2u32.pow(n);
+
+        for _ in 0..100 {
+            assert!(registry.retry_delay(n) <= backoff);
+        }
+    }
+}
+
+#[tokio::test]
+async fn it_gives_up_after_max_retries() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server = server::http({
+        let requests = requests.clone();
+
+        move |_req| {
+            requests.fetch_add(1, Ordering::SeqCst);
+
+            async move {
+                http::Response::builder()
+                    .status(429)
+                    .header("Retry-After", "0")
+                    .body(r#"{"message":"rate limited"}"#.into())
+                    .unwrap()
+            }
+        }
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).max_retries(2);
+    let err = registry
+        .delete_image_by_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960", false)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "API error (429 Too Many Requests): rate limited"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn it_treats_missing_tags_as_deleted_when_idempotent() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "DELETE");
+        assert_eq!(req.uri(), "/tags/5e3b5f16-afe2-44a4-93d5-3cbebee14960");
+
+        http::Response::builder()
+            .status(404)
+            .body(r#"{"message":"resource is not found"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .delete_image_by_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960", false)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "API error (404 Not Found): resource is not found"
+    );
+
+    let deleted = registry
+        .idempotent_deletes(true)
+        .delete_image_by_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960", false)
+        .await
+        .unwrap();
+
+    assert!(deleted.is_none());
+}
+
+#[tokio::test]
+async fn it_sends_the_expected_digest_when_deleting_conditionally() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "DELETE");
+        assert_eq!(req.headers()["If-Match"], "\"sha256:1675dda5\"");
+
+        http::Response::builder()
+            .body(include_str!("fixtures/image_tag.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let deleted = new_registry(&endpoint)
+        .delete_image_by_tag_if_match(
+            "5e3b5f16-afe2-44a4-93d5-3cbebee14960",
+            "sha256:1675dda5",
+            false,
+        )
+        .await
+        .unwrap();
+
+    assert!(deleted.is_some());
+}
+
+#[tokio::test]
+async fn it_reports_tags_that_changed_before_a_conditional_deletion() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(412)
+            .body(r#"{"message":"precondition failed"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let err = new_registry(&endpoint)
+        .delete_image_by_tag_if_match(
+            "5e3b5f16-afe2-44a4-93d5-3cbebee14960",
+            "sha256:1675dda5",
+            false,
+        )
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::TagChanged { tag_id } => {
+            assert_eq!(tag_id, "5e3b5f16-afe2-44a4-93d5-3cbebee14960")
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn it_returns_http_status_when_unauthorized() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(401)
+            .body(r#"{"message":"authentication is denied"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .image_tags("b00f6b0a-cc14-4c21-843f-3acda6ebb001")
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::Http {
+            status,
+            message,
+            request_id,
+        } => {
+            assert_eq!(status, scaleway_sdk::StatusCode::UNAUTHORIZED);
+            assert_eq!(message, "authentication is denied");
+            assert_eq!(request_id, None);
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn it_includes_the_request_id_in_api_errors() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .status(409)
+            .header("X-Request-Id", "0c3f4a1e-8d2b-4e7a-9f61-2b5d7c9e1a34")
+            .body(r#"{"message":"tag is shared with other tags"}"#.into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint);
+    let err = registry
+        .delete_image_by_tag("5e3b5f16-afe2-44a4-93d5-3cbebee14960", false)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "API error (409 Conflict): tag is shared with other tags \
+         (request id: 0c3f4a1e-8d2b-4e7a-9f61-2b5d7c9e1a34)"
+    );
+}
+
+#[tokio::test]
+async fn it_times_out_slow_requests() {
+    let server = server::http(move |_req| async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        http::Response::builder()
+            .body(include_str!("fixtures/namespace_list.json").into())
+            .unwrap()
+    });
+
+    let endpoint = format!("http://{}", server.addr());
+    let registry = new_registry(&endpoint).timeout(Duration::from_millis(50));
+    let err = registry.namespaces().await.unwrap_err();
+
+    match err {
+        Error::ReqwestError(err) => assert!(err.is_timeout()),
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
@@ -0,0 +1,38 @@
+use scaleway_sdk::registry::Status;
+
+#[test]
+fn it_round_trips_status_strings() {
+    for s in &[
+        "unknown",
+        "ready",
+        "deleting",
+        "deleted",
+        "transient",
+        "error",
+        "locked",
+        "frobnicating",
+    ] {
+        let status = s.parse::<Status>().unwrap();
+
+        assert_eq!(status.to_string(), *s);
+        assert_eq!(status.as_ref(), *s);
+    }
+}
+
+#[test]
+fn it_keeps_unknown_status_strings() {
+    assert_eq!(
+        "frobnicating".parse::<Status>().unwrap(),
+        Status::Other("frobnicating".to_owned())
+    );
+    assert_eq!("unknown".parse::<Status>().unwrap(), Status::Unknown);
+}
+
+#[test]
+fn it_serializes_status_as_string() {
+    assert_eq!(serde_json::to_string(&Status::Ready).unwrap(), r#""ready""#);
+    assert_eq!(
+        serde_json::to_string(&Status::Other("frobnicating".to_owned())).unwrap(),
+        r#""frobnicating""#
+    );
+}
@@ -11,7 +11,6 @@ use std::time::Duration;
 
 use tokio::sync::oneshot;
 
-pub use http::Response;
 use tokio::runtime;
 
 pub struct Server {
@@ -51,7 +50,8 @@ where
             .enable_all()
             .build()
             .expect("new rt");
-        let srv = rt.block_on(async move {
+        let srv = {
+            let _guard = rt.enter();
             hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(hyper::service::make_service_fn(
                 move |_| {
                     let func = func.clone();
@@ -63,7 +63,7 @@ where
                     }
                 },
             ))
-        });
+        };
 
         let addr = srv.local_addr();
         let (shutdown_tx, shutdown_rx) = oneshot::channel();